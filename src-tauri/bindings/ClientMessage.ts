@@ -1,9 +1,19 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FindMatch } from "./FindMatch";
+import type { FindQuery } from "./FindQuery";
 import type { Note } from "./Note";
+import type { PartialAction } from "./PartialAction";
 import type { PartialActionFilter } from "./PartialActionFilter";
+import type { PropertyQuery } from "./PropertyQuery";
 import type { SearcherId } from "./SearcherId";
 import type { Settings } from "./Settings";
 import type { SourceChange } from "./SourceChange";
 import type { SuggesterSource } from "./SuggesterSource";
+import type { TagConfig } from "./TagConfig";
+import type { TagNormalization } from "./TagNormalization";
 
-export type ClientMessage = { "type": "getSettings" } | { "type": "updateSettings", "data": { settings: Settings, } } | { "type": "getNote", "data": { path: string, } } | { "type": "updateNote", "data": { path: string, note: Note, } } | { "type": "updatePath", "data": { currentPath: string, newTitle: string, } } | { "type": "createNote", "data": { title: string, } } | { "type": "createPalette", "data": { paletteKey: string, filters: Array<PartialActionFilter>, } } | { "type": "deletePalette", "data": { id: SearcherId, } } | { "type": "createSuggester", "data": { suggesterSource: SuggesterSource, } } | { "type": "searchSuggester", "data": { id: SearcherId, search: string, } } | { "type": "deleteSuggester", "data": { id: SearcherId, } } | { "type": "searchPalette", "data": { id: SearcherId, search: string, start: number, end: number, } } | { "type": "addPinned", "data": { path: string, position: number, } } | { "type": "removePinned", "data": { path: string, } } | { "type": "getPinned" } | { "type": "getActions" } | { "type": "getTagConfigs" } | { "type": "refresh" } | { "type": "runCode", "data": { code: string, } } | { "type": "previewerUpdateSource", "data": { change: SourceChange, editorViewId: string, } } | { "type": "previewerCloseEditorView", "data": { editorViewId: string, } };
+export type ClientMessage = { "type": "getSettings" } | { "type": "updateSettings", "data": { settings: Settings, } } | { "type": "switchVault", "data": { name: string, } } | { "type": "setWindowVault", "data": { name: string | null, } } | { "type": "getNote", "data": { path: string, } } | { "type": "getNoteMetas", "data": { paths: Array<string> | null, } } | { "type": "searchContent", "data": { query: string, limit: number | null, } } | { "type": "findInNotes", "data": { query: FindQuery, } } | { "type": "replaceInNotes", "data": { query: FindQuery, replacement: string, matches: Array<FindMatch>, } } | { "type": "undoReplace" } | { "type": "queryNotes", "data": { query: string, } } | { "type": "queryProperties", "data": { query: PropertyQuery, } } | { "type": "updateNote", "data": { path: string, note: Note, } } | { "type": "updatePath", "data": { currentPath: string, newTitle: string, } } | { "type": "createNote", "data": { title: string, } } | { "type": "deleteNote", "data": { path: string, } } | { "type": "listTrash" } | { "type": "restoreTrash", "data": { id: string, } } | { "type": "purgeTrash", "data": { maxAgeDays: number | null, } } | { "type": "importAttachment", "data": { name: string, bytes: string, } } | { "type": "listAttachments" } | { "type": "listNoteRevisions", "data": { path: string, } } | { "type": "diffNoteRevisions", "data": { path: string, from: string, to: string | null, } } | { "type": "restoreNoteRevision", "data": { path: string, revision: string, 
+/**
+ * revision of the note as the client last saw it
+ */
+currentRevision: string, } } | { "type": "createPalette", "data": { paletteKey: string, filters: Array<PartialActionFilter>, } } | { "type": "deletePalette", "data": { id: SearcherId, } } | { "type": "chooseAction", "data": { action: PartialAction, path: string | null, } } | { "type": "listSearchers" } | { "type": "createSuggester", "data": { suggesterSource: SuggesterSource, } } | { "type": "searchSuggester", "data": { id: SearcherId, search: string, } } | { "type": "deleteSuggester", "data": { id: SearcherId, } } | { "type": "searchPalette", "data": { id: SearcherId, search: string, start: number, end: number, } } | { "type": "addPinned", "data": { path: string, position: number, } } | { "type": "removePinned", "data": { path: string, } } | { "type": "getPinned" } | { "type": "getActions" } | { "type": "getTagConfigs" } | { "type": "getTagTree" } | { "type": "setTagConfig", "data": { tag: string, config: TagConfig, } } | { "type": "clearTagConfig", "data": { tag: string, } } | { "type": "getTagDuplicates" } | { "type": "getTagNormalization" } | { "type": "setTagNormalization", "data": { normalization: TagNormalization, } } | { "type": "renameTag", "data": { from: string, to: string, dryRun: boolean, } } | { "type": "mergeTag", "data": { from: string, into: string, dryRun: boolean, } } | { "type": "refresh" } | { "type": "runCode", "data": { code: string, } } | { "type": "previewerUpdateSource", "data": { change: SourceChange, editorViewId: string, } } | { "type": "previewerCloseEditorView", "data": { editorViewId: string, } };
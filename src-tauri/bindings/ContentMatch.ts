@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Utf16Index } from "./Utf16Index";
+
+export type ContentMatch = { path: string, 
+/**
+ * BM25 score, higher is better
+ */
+score: number, 
+/**
+ * the text around the first match, on a single line
+ */
+snippet: string, 
+/**
+ * ranges of the matches within the snippet
+ */
+snippetHighlights: Array<{ start: Utf16Index, end: Utf16Index, }>, 
+/**
+ * ranges of the matches within the note, for the editor to jump to
+ */
+ranges: Array<{ start: Utf16Index, end: Utf16Index, }>, };
@@ -0,0 +1,25 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Utf16Index } from "./Utf16Index";
+
+export type FindMatch = { path: string, 
+/**
+ * revision of the note the match was found in, so a replace can tell the note changed
+ * since
+ */
+revision: string, 
+/**
+ * line the match starts on, counting from 1
+ */
+line: number, 
+/**
+ * the text of that line around the match
+ */
+context: string, 
+/**
+ * range of the match within the context
+ */
+contextRange: { start: Utf16Index, end: Utf16Index, }, 
+/**
+ * range of the match within the note
+ */
+range: { start: Utf16Index, end: Utf16Index, }, };
\ No newline at end of file
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type FindQuery = { pattern: string, 
+/**
+ * treat the pattern as a regular expression rather than literal text. The replacement can
+ * then refer to groups like `$1`.
+ */
+regex: boolean, caseSensitive: boolean, };
\ No newline at end of file
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type Locater = `note:${string}` | 'pinned' | 'settings' | 'new';
+export type Locater = `note:${string}` | `vault:${string}:note:${string}` | 'pinned' | 'settings' | 'new';
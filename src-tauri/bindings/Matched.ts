@@ -1,3 +1,12 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FieldMatch } from "./FieldMatch";
 
-export type Matched<T> = { indices: Array<number>, payload: T, };
+export type Matched<T> = { 
+/**
+ * positions of the matched characters in the field the item is shown by
+ */
+indices: Array<number>, 
+/**
+ * every field the search matched, with the positions of the matched characters in it
+ */
+fields: Array<FieldMatch>, payload: T, };
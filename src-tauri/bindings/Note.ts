@@ -1,4 +1,9 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { NoteMeta } from "./NoteMeta";
 
-export type Note = { content: string, meta: NoteMeta, };
+export type Note = { content: string, meta: NoteMeta, 
+/**
+ * revision of the content on disk this note is based on. Writing a note whose revision
+ * no longer matches the disk is rejected, writing one without a revision always succeeds.
+ */
+revision?: string, };
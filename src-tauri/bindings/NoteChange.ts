@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NoteChangeKind } from "./NoteChangeKind";
+
+export type NoteChange = { path: string, kind: NoteChangeKind, };
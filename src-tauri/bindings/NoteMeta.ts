@@ -1,3 +1,15 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type NoteMeta = { selection?: [number, number], };
+export type NoteMeta = { selection?: [number, number], 
+/**
+ * seconds since the unix epoch, None for notes that existed before brot kept track
+ */
+created?: number, 
+/**
+ * when the content last changed, in seconds since the unix epoch
+ */
+modified?: number, 
+/**
+ * when the note was last opened, in seconds since the unix epoch
+ */
+opened?: number, openCount: number, wordCount: number, charCount: number, };
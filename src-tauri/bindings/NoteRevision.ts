@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type NoteRevision = { id: string, 
+/**
+ * path the note had at this revision
+ */
+path: string, 
+/**
+ * seconds since the unix epoch
+ */
+timestamp: number, message: string, };
@@ -1,4 +1,16 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { PartialAction } from "./PartialAction";
 
-export type PaletteAction = { title: string, path: string | null, icon: string | null, shortcut: string | null, action: PartialAction, };
+export type PaletteAction = { title: string, path: string | null, icon: string | null, shortcut: string | null, action: PartialAction, 
+/**
+ * tags of the note the action is about, like "-project--alpha"
+ */
+tags: Array<string>, 
+/**
+ * aliases of those tags
+ */
+tagAliases: Array<string>, 
+/**
+ * aliases the note declares in its front matter
+ */
+aliases: Array<string>, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PaletteAction } from "./PaletteAction";
+import type { SearchResults } from "./SearchResults";
+import type { SearcherId } from "./SearcherId";
+
+/**
+ * Results of a palette search that nucleo was still matching when it was answered
+ */
+export type PaletteResults = { id: SearcherId, search: string, start: number, end: number, results: SearchResults<PaletteAction>, };
@@ -1,3 +1,8 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type PartialActionGenerator = { key: string, args: Array<string>, };
+export type PartialActionGenerator = { key: string, args: Array<string>, 
+/**
+ * a [`NoteQuery`](crate::message::note_query::NoteQuery) limiting which notes `$note_path`
+ * and `$note_locater` generate actions for
+ */
+query?: string, };
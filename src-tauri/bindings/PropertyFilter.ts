@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FilterOp } from "./FilterOp";
+
+export type PropertyFilter = { property: string, op: FilterOp, 
+/**
+ * value to compare against, unused by `exists` and `missing`
+ */
+value?: unknown, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PropertyFilter } from "./PropertyFilter";
+import type { PropertySort } from "./PropertySort";
+
+export type PropertyQuery = { 
+/**
+ * notes have to match every filter
+ */
+filters: Array<PropertyFilter>, 
+/**
+ * sorts by the first entry, then the next for notes that are equal, and so on
+ */
+sort: Array<PropertySort>, 
+/**
+ * properties that become the columns of the table. Besides front matter properties,
+ * `file.path`, `file.title` and the fields of NoteMeta like `file.modified` can be used.
+ */
+select: Array<string>, limit?: number, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type PropertyRow = { path: string, 
+/**
+ * one value per column, null where the note doesn't have the property
+ */
+values: Array<unknown>, };
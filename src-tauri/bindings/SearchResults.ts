@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Matched } from "./Matched";
+
+/**
+ * A page of a searcher's results
+ */
+export type SearchResults<T> = { matches: Array<Matched<T>>, 
+/**
+ * how many items matched in total
+ */
+count: number, 
+/**
+ * whether nucleo is still matching, in which case searching again can find more. The
+ * window that searched a palette is sent its complete results once nucleo is done.
+ */
+running: boolean, };
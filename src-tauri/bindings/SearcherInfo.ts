@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SearcherId } from "./SearcherId";
+
+/**
+ * A live searcher, for debugging leaks
+ */
+export type SearcherInfo = { id: SearcherId, 
+/**
+ * label of the window that created the searcher
+ */
+window: string | null, itemCount: number, 
+/**
+ * rough size of the items and their search columns in bytes
+ */
+memory: number, ageSecs: number, idleSecs: number, };
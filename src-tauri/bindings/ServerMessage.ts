@@ -1,16 +1,29 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { Actions } from "./Actions";
 import type { CodeResult } from "./CodeResult";
+import type { ContentMatch } from "./ContentMatch";
+import type { FindMatch } from "./FindMatch";
 import type { Matched } from "./Matched";
 import type { Note } from "./Note";
+import type { NoteMeta } from "./NoteMeta";
+import type { NoteRevision } from "./NoteRevision";
 import type { PaletteAction } from "./PaletteAction";
 import type { PreviewerResult } from "./PreviewerResult";
+import type { PropertyTable } from "./PropertyTable";
+import type { SearchResults } from "./SearchResults";
 import type { SearcherId } from "./SearcherId";
 import type { Settings } from "./Settings";
 import type { Suggestion } from "./Suggestion";
 import type { TagConfig } from "./TagConfig";
+import type { TagDuplicate } from "./TagDuplicate";
+import type { TagNode } from "./TagNode";
+import type { TagNormalization } from "./TagNormalization";
+import type { TagRewrite } from "./TagRewrite";
+import type { TrashEntry } from "./TrashEntry";
+import type { VaultSearchers } from "./VaultSearchers";
+import type { WriteNoteResult } from "./WriteNoteResult";
 
 /**
  * ServerMessage's enum variant names must match ClientMessage's enum variant names
  */
-export type ServerMessage = { "type": "getSettings", "data": Settings } | { "type": "updateSettings" } | { "type": "getNote", "data": Note | null } | { "type": "updateNote" } | { "type": "updatePath", "data": string | null } | { "type": "createNote", "data": string | null } | { "type": "note", "data": Note | null } | { "type": "createPalette", "data": SearcherId } | { "type": "searchPalette", "data": Array<Matched<PaletteAction>> | null } | { "type": "deletePalette" } | { "type": "createSuggester", "data": SearcherId } | { "type": "searchSuggester", "data": Array<Matched<Suggestion>> | null } | { "type": "deleteSuggester" } | { "type": "addPinned" } | { "type": "removePinned" } | { "type": "getPinned", "data": Array<string> } | { "type": "getActions", "data": Actions } | { "type": "getTagConfigs", "data": { [key in string]?: TagConfig } } | { "type": "refresh" } | { "type": "runCode", "data": CodeResult } | { "type": "previewerUpdateSource", "data": PreviewerResult } | { "type": "previewerCloseEditorView" };
+export type ServerMessage = { "type": "getSettings", "data": Settings } | { "type": "updateSettings" } | { "type": "switchVault" } | { "type": "setWindowVault" } | { "type": "getNote", "data": Note | null } | { "type": "getNoteMetas", "data": { [key in string]?: NoteMeta } } | { "type": "searchContent", "data": Array<ContentMatch> } | { "type": "findInNotes", "data": Array<FindMatch> } | { "type": "replaceInNotes", "data": Array<string> } | { "type": "undoReplace", "data": Array<string> } | { "type": "queryNotes", "data": Array<string> } | { "type": "queryProperties", "data": PropertyTable } | { "type": "updateNote", "data": WriteNoteResult } | { "type": "updatePath", "data": string | null } | { "type": "createNote", "data": string | null } | { "type": "deleteNote" } | { "type": "listTrash", "data": Array<TrashEntry> } | { "type": "restoreTrash", "data": string } | { "type": "purgeTrash", "data": Array<string> } | { "type": "importAttachment", "data": string } | { "type": "listAttachments", "data": Array<string> } | { "type": "listNoteRevisions", "data": Array<NoteRevision> } | { "type": "diffNoteRevisions", "data": string } | { "type": "restoreNoteRevision", "data": WriteNoteResult } | { "type": "note", "data": Note | null } | { "type": "createPalette", "data": SearcherId } | { "type": "searchPalette", "data": SearchResults<PaletteAction> | null } | { "type": "deletePalette" } | { "type": "chooseAction" } | { "type": "listSearchers", "data": Array<VaultSearchers> } | { "type": "createSuggester", "data": SearcherId } | { "type": "searchSuggester", "data": Array<Matched<Suggestion>> | null } | { "type": "deleteSuggester" } | { "type": "addPinned" } | { "type": "removePinned" } | { "type": "getPinned", "data": Array<string> } | { "type": "getActions", "data": Actions } | { "type": "getTagConfigs", "data": { [key in string]?: TagConfig } } | { "type": "getTagTree", "data": Array<TagNode> } | { "type": "setTagConfig" } | { "type": "clearTagConfig" } | { "type": "getTagDuplicates", "data": Array<TagDuplicate> } | { "type": "getTagNormalization", "data": TagNormalization } | { "type": "setTagNormalization" } | { "type": "renameTag", "data": TagRewrite } | { "type": "mergeTag", "data": TagRewrite } | { "type": "refresh" } | { "type": "runCode", "data": CodeResult } | { "type": "previewerUpdateSource", "data": PreviewerResult } | { "type": "previewerCloseEditorView" };
@@ -1,5 +1,18 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { Locater } from "./Locater";
+import type { Vault } from "./Vault";
 import type { WindowState } from "./WindowState";
 
-export type Settings = { notesPath: string | null, windowStates: { [key in Locater]?: WindowState }, minimizedPinnedPaths?: Array<string>, };
+export type Settings = { vaults: Array<Vault>, 
+/**
+ * name of the vault notes are read from, the first vault is used if it is None
+ */
+activeVault?: string, windowStates: { [key in Locater]?: WindowState }, minimizedPinnedPaths?: Array<string>, 
+/**
+ * names or relative paths in the notes folder that are left out of the vault
+ */
+ignoredPaths?: Array<string>, 
+/**
+ * how many days trashed notes are kept before purging the trash removes them
+ */
+trashRetentionDays?: number, };
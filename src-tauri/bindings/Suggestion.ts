@@ -1,3 +1,13 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TagSource } from "./TagSource";
 
-export type Suggestion = { value: string, };
+export type Suggestion = { value: string, 
+/**
+ * where a suggested tag is written, so tags from note contents can be told apart from
+ * file name tags
+ */
+sources: Array<TagSource>, 
+/**
+ * the alias that was matched, when the suggestion is the tag an alias stands for
+ */
+alias: string | null, };
@@ -1,3 +1,16 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type TagConfig = { abbreviation: string | null, hue: number | null, };
+export type TagConfig = { abbreviation: string | null, hue: number | null, 
+/**
+ * name of an icon shown next to the tag
+ */
+icon: string | null, description: string | null, 
+/**
+ * position among the tag's siblings in the tag tree, lowest first
+ */
+sortOrder: number | null, 
+/**
+ * other tags that mean this one, like "ml" for "machine-learning". They are folded into
+ * this tag in the tag tree and in queries.
+ */
+aliases: Array<string>, };
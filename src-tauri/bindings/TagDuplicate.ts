@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DuplicateReason } from "./DuplicateReason";
+
+/**
+ * Two tags that are probably meant to be the same one
+ */
+export type TagDuplicate = { 
+/**
+ * the tags the way they are written in file names, like "-machine-learning"
+ */
+first: string, second: string, reason: DuplicateReason, };
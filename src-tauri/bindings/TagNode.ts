@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TagSource } from "./TagSource";
+
+export type TagNode = { name: string, children: Array<TagNode>, 
+/**
+ * where the tag or any of its subtags are written
+ */
+sources: Array<TagSource>, 
+/**
+ * how many notes have the tag or any of its subtags
+ */
+noteCount: number, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * How tag names are normalized before they are compared and put in the tag tree
+ */
+export type TagNormalization = { 
+/**
+ * fold case, so `-ML` and `-ml` are the same tag
+ */
+case: boolean, 
+/**
+ * apply unicode compatibility normalization (NFKC), so `-ﬁle` and `-file` are the same tag
+ */
+unicode: boolean, };
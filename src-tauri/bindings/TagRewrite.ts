@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Rename } from "./Rename";
+
+export type TagRewrite = { 
+/**
+ * notes that were renamed, or would be on a dry run
+ */
+notes: Array<Rename>, 
+/**
+ * notes that are left alone because another note already has their new path
+ */
+conflicts: Array<Rename>, 
+/**
+ * tag config keys that move along with the tag
+ */
+tagConfigs: Array<Rename>, 
+/**
+ * notes that have the tag as a `#tag` or in their front matter, which is left alone
+ */
+contentTagged: Array<string>, };
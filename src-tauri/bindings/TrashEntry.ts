@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NoteMeta } from "./NoteMeta";
+
+export type TrashEntry = { id: string, 
+/**
+ * path the note had before it was trashed, where restoring puts it back
+ */
+originalPath: string, trashPath: string, meta: NoteMeta, 
+/**
+ * index the note had in the pinned list, if it was pinned
+ */
+pinnedPosition?: number, 
+/**
+ * seconds since the unix epoch
+ */
+deletedAt: number, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SearcherInfo } from "./SearcherInfo";
+
+/**
+ * The live searchers of a vault
+ */
+export type VaultSearchers = { vault: string, palettes: Array<SearcherInfo>, suggesters: Array<SearcherInfo>, };
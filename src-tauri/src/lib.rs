@@ -1,11 +1,14 @@
+use message::searcher::delete_window_searchers;
 use message::{handle_message_and_errors, ClientMessage};
 use missed_events::set_event_ready;
 use state::AppState;
-use tauri::{Manager, State};
+use tauri::{webview::PageLoadEvent, Manager, State};
 
 #[cfg(not(target_os = "android"))]
 use tauri_plugin_global_shortcut::{Code, Modifiers, ShortcutState};
 
+#[cfg(not(target_os = "android"))]
+use message::attachment::import_attachment_file;
 #[cfg(not(target_os = "android"))]
 use window::{complete_search, open_search, open_window};
 
@@ -19,6 +22,7 @@ pub mod missed_events;
 pub mod previewer;
 pub mod server;
 pub mod state;
+pub mod watcher;
 pub mod window_state;
 
 #[cfg(not(target_os = "android"))]
@@ -40,9 +44,18 @@ fn is_android() -> bool {
 #[tauri::command]
 async fn message_command(
     message: ClientMessage,
+    window: tauri::Window,
     state: State<'_, AppState>,
 ) -> Result<ServerResult, String> {
-    Ok(handle_message_and_errors(message, &state).await)
+    Ok(handle_message_and_errors(message, &state, Some(window.label())).await)
+}
+
+/// Writes meta changes that are still waiting for their debounce before the process ends
+fn flush_meta_on_exit(app_handle: &tauri::AppHandle) {
+    let state = app_handle.state::<AppState>();
+    if let Err(err) = tauri::async_runtime::block_on(message::meta::flush_all_meta(&state)) {
+        eprintln!("failed to write meta on exit: {:?}", err);
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -55,6 +68,18 @@ pub fn run() {
         .setup(|app| {
             let state = AppState::new(app).expect("failed to init app state");
             app.manage(state.clone());
+            {
+                let state = state.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = watcher::restart_watcher(&state).await {
+                        eprintln!("failed to watch notes folder: {:?}", e);
+                    }
+                    // loading the meta indexes every note, so searches don't wait for it later
+                    if let Err(e) = message::meta::read_meta(&state, |_| ()).await {
+                        eprintln!("failed to load notes: {:?}", e);
+                    }
+                });
+            }
             // app.set_activation_policy(ActivationPolicy::Accessory);
             #[cfg(not(any(target_os = "android", target_os = "ios")))]
             {
@@ -69,6 +94,16 @@ pub fn run() {
             }
             Ok(())
         })
+        .on_page_load(|webview, payload| {
+            // a reloaded page starts over, so the searchers it created can't be used anymore
+            if let PageLoadEvent::Started = payload.event() {
+                let state = webview.state::<AppState>().inner().clone();
+                let label = webview.label().to_owned();
+                tauri::async_runtime::spawn(async move {
+                    delete_window_searchers(&state, &label).await;
+                });
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             message_command,
             is_android,
@@ -79,6 +114,8 @@ pub fn run() {
             update_window_state,
             #[cfg(not(target_os = "android"))]
             complete_search,
+            #[cfg(not(target_os = "android"))]
+            import_attachment_file,
         ]);
 
     let app = {
@@ -139,13 +176,19 @@ pub fn run() {
     #[cfg(not(target_os = "android"))]
     app.build(tauri::generate_context!())
         .expect("error building app")
-        .run(|_app_handle, event| match event {
+        .run(|app_handle, event| match event {
             tauri::RunEvent::ExitRequested { api, .. } => {
                 api.prevent_exit();
             }
+            tauri::RunEvent::Exit => flush_meta_on_exit(app_handle),
             _ => {}
         });
     #[cfg(target_os = "android")]
-    app.run(tauri::generate_context!())
-        .expect("err while running");
+    app.build(tauri::generate_context!())
+        .expect("err while running")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                flush_meta_on_exit(app_handle);
+            }
+        });
 }
@@ -1,42 +1,71 @@
 use std::collections::HashMap;
+use std::path::Path;
 
-use meta::{read_meta, write_meta};
-use note::Note;
-use note::{create_note, read_note, write_note};
+use meta::{flush_meta, read_meta, write_meta};
+use note::{Note, WriteNoteResult};
+use note::{NoteMeta, create_note, read_note, read_note_metas, write_note};
 use serde::{Deserialize, Serialize};
-use settings::{Settings, write_settings};
+use serde_with::{base64::Base64, serde_as};
+use settings::{Settings, set_window_vault, switch_vault, write_settings};
 use ts_rs::TS;
 
-use crate::message::action::{Actions, PartialActionFilter, read_actions};
+use crate::message::action::{Actions, PartialAction, PartialActionFilter, read_actions};
+use crate::message::attachment::{import_attachment, list_attachments};
+use crate::message::content_search::{ContentMatch, search_content};
+use crate::message::find_replace::{
+    FindMatch, FindQuery, find_in_notes, replace_in_notes, undo_replace,
+};
+use crate::message::frecency::record_choice;
+use crate::message::history::{NoteRevision, diff_revisions, list_revisions, restore_revision};
 use crate::message::meta::TagConfig;
-use crate::message::note::update_path;
+use crate::message::note::{delete_note, update_path};
+use crate::message::note_query::{NoteQuery, query_notes};
 use crate::message::palette::{create_palette, delete_palette, search_palette};
-use crate::message::palette_action::{Matched, PaletteAction};
+use crate::message::palette_action::{Matched, PaletteAction, SearchResults};
+use crate::message::property::{PropertyQuery, PropertyTable, query_properties};
 use crate::message::run_code::{CodeResult, run_python};
-use crate::message::searcher::SearcherId;
+use crate::message::searcher::{SearcherId, VaultSearchers, list_searchers};
 use crate::message::settings::read_settings_file;
 use crate::message::suggester::{
     SuggesterSource, Suggestion, create_suggester, delete_suggester, search_suggester,
 };
+use crate::message::tag::{TagNode, clear_tag_config, get_tag_tree, set_tag_config};
+use crate::message::tag_alias::{
+    TagDuplicate, TagNormalization, find_duplicate_tags, set_tag_normalization,
+};
+use crate::message::tag_rename::{TagRewrite, merge_tag, rename_tag};
+use crate::message::trash::{TrashEntry, list_trash, purge_trash, restore_trash};
 use crate::previewer::{PreviewerResult, SourceChange};
 use crate::state::AppState;
 
 use anyhow::Result;
 
 pub mod action;
+pub mod attachment;
+pub mod content_search;
+pub mod find_replace;
 pub mod folder_manager;
+pub mod frecency;
+pub mod history;
 pub mod locater;
 pub mod meta;
 pub mod note;
+pub mod note_query;
 pub mod palette;
 pub mod palette_action;
+pub mod property;
 pub mod run_code;
 pub mod searcher;
 pub mod settings;
+pub mod store;
 pub mod suggester;
 pub mod tag;
+pub mod tag_alias;
+pub mod tag_rename;
 pub mod title;
+pub mod trash;
 
+#[serde_as]
 #[derive(Serialize, Deserialize, TS)]
 #[ts(export)]
 #[serde(tag = "type", content = "data", rename_all = "camelCase")]
@@ -47,10 +76,46 @@ pub enum ClientMessage {
         settings: Settings,
     },
     #[serde(rename_all = "camelCase")]
+    SwitchVault {
+        name: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    SetWindowVault {
+        name: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
     GetNote {
         path: String,
     },
     #[serde(rename_all = "camelCase")]
+    GetNoteMetas {
+        paths: Option<Vec<String>>,
+    },
+    #[serde(rename_all = "camelCase")]
+    SearchContent {
+        query: String,
+        limit: Option<u32>,
+    },
+    #[serde(rename_all = "camelCase")]
+    FindInNotes {
+        query: FindQuery,
+    },
+    #[serde(rename_all = "camelCase")]
+    ReplaceInNotes {
+        query: FindQuery,
+        replacement: String,
+        matches: Vec<FindMatch>,
+    },
+    UndoReplace,
+    #[serde(rename_all = "camelCase")]
+    QueryNotes {
+        query: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    QueryProperties {
+        query: PropertyQuery,
+    },
+    #[serde(rename_all = "camelCase")]
     UpdateNote {
         path: String,
         note: Note,
@@ -65,6 +130,44 @@ pub enum ClientMessage {
         title: String,
     },
     #[serde(rename_all = "camelCase")]
+    DeleteNote {
+        path: String,
+    },
+    ListTrash,
+    #[serde(rename_all = "camelCase")]
+    RestoreTrash {
+        id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    PurgeTrash {
+        max_age_days: Option<u32>,
+    },
+    #[serde(rename_all = "camelCase")]
+    ImportAttachment {
+        name: String,
+        #[serde_as(as = "Base64")]
+        #[ts(type = "string")]
+        bytes: Vec<u8>,
+    },
+    ListAttachments,
+    #[serde(rename_all = "camelCase")]
+    ListNoteRevisions {
+        path: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    DiffNoteRevisions {
+        path: String,
+        from: String,
+        to: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    RestoreNoteRevision {
+        path: String,
+        revision: String,
+        /// revision of the note as the client last saw it
+        current_revision: String,
+    },
+    #[serde(rename_all = "camelCase")]
     CreatePalette {
         palette_key: String,
         filters: Vec<PartialActionFilter>,
@@ -74,6 +177,12 @@ pub enum ClientMessage {
         id: SearcherId,
     },
     #[serde(rename_all = "camelCase")]
+    ChooseAction {
+        action: PartialAction,
+        path: Option<String>,
+    },
+    ListSearchers,
+    #[serde(rename_all = "camelCase")]
     CreateSuggester {
         suggester_source: SuggesterSource,
     },
@@ -105,6 +214,34 @@ pub enum ClientMessage {
     GetPinned,
     GetActions,
     GetTagConfigs,
+    GetTagTree,
+    #[serde(rename_all = "camelCase")]
+    SetTagConfig {
+        tag: String,
+        config: TagConfig,
+    },
+    #[serde(rename_all = "camelCase")]
+    ClearTagConfig {
+        tag: String,
+    },
+    GetTagDuplicates,
+    GetTagNormalization,
+    #[serde(rename_all = "camelCase")]
+    SetTagNormalization {
+        normalization: TagNormalization,
+    },
+    #[serde(rename_all = "camelCase")]
+    RenameTag {
+        from: String,
+        to: String,
+        dry_run: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    MergeTag {
+        from: String,
+        into: String,
+        dry_run: bool,
+    },
     Refresh,
     RunCode {
         code: String,
@@ -127,14 +264,34 @@ pub enum ClientMessage {
 pub enum ServerMessage {
     GetSettings(Settings),
     UpdateSettings,
+    SwitchVault,
+    SetWindowVault,
     GetNote(Option<Note>),
-    UpdateNote,
+    GetNoteMetas(HashMap<String, NoteMeta>),
+    SearchContent(Vec<ContentMatch>),
+    FindInNotes(Vec<FindMatch>),
+    ReplaceInNotes(Vec<String>),
+    UndoReplace(Vec<String>),
+    QueryNotes(Vec<String>),
+    QueryProperties(PropertyTable),
+    UpdateNote(WriteNoteResult),
     UpdatePath(Option<String>),
     CreateNote(Option<String>),
+    DeleteNote,
+    ListTrash(Vec<TrashEntry>),
+    RestoreTrash(String),
+    PurgeTrash(Vec<String>),
+    ImportAttachment(String),
+    ListAttachments(Vec<String>),
+    ListNoteRevisions(Vec<NoteRevision>),
+    DiffNoteRevisions(String),
+    RestoreNoteRevision(WriteNoteResult),
     Note(Option<Note>),
     CreatePalette(SearcherId),
-    SearchPalette(Option<Vec<Matched<PaletteAction>>>),
+    SearchPalette(Option<SearchResults<PaletteAction>>),
     DeletePalette,
+    ChooseAction,
+    ListSearchers(Vec<VaultSearchers>),
     CreateSuggester(SearcherId),
     SearchSuggester(Option<Vec<Matched<Suggestion>>>),
     DeleteSuggester,
@@ -143,14 +300,34 @@ pub enum ServerMessage {
     GetPinned(Vec<String>),
     GetActions(Actions),
     GetTagConfigs(HashMap<String, TagConfig>),
+    GetTagTree(Vec<TagNode>),
+    SetTagConfig,
+    ClearTagConfig,
+    GetTagDuplicates(Vec<TagDuplicate>),
+    GetTagNormalization(TagNormalization),
+    SetTagNormalization,
+    RenameTag(TagRewrite),
+    MergeTag(TagRewrite),
     Refresh,
     RunCode(CodeResult),
     PreviewerUpdateSource(PreviewerResult),
     PreviewerCloseEditorView,
 }
 
-pub async fn handle_message(message: ClientMessage, state: &AppState) -> Result<ServerMessage> {
+/// Handles a message sent by the window labeled `window`, or by a browser when there's no
+/// window
+pub async fn handle_message(
+    message: ClientMessage,
+    state: &AppState,
+    window: Option<&str>,
+) -> Result<ServerMessage> {
     use ClientMessage::*;
+    // a window bound to a vault reads and writes that vault, except when it is being rebound,
+    // which must work even if the vault it was bound to is gone
+    let state = &match message {
+        SetWindowVault { .. } => state.clone(),
+        _ => state.for_window(window).await?,
+    };
     match message {
         GetSettings => Ok(ServerMessage::GetSettings(
             state.settings.lock().await.clone(),
@@ -159,11 +336,41 @@ pub async fn handle_message(message: ClientMessage, state: &AppState) -> Result<
             write_settings(state, settings).await?;
             Ok(ServerMessage::UpdateSettings)
         }
-        GetNote { path } => Ok(ServerMessage::Note(read_note(state, &path).await?)),
-        UpdateNote { path, note } => {
-            write_note(state, &path, note).await?;
-            Ok(ServerMessage::UpdateNote)
+        SwitchVault { name } => {
+            switch_vault(state, name).await?;
+            Ok(ServerMessage::SwitchVault)
+        }
+        SetWindowVault { name } => {
+            set_window_vault(state, window, name).await?;
+            Ok(ServerMessage::SetWindowVault)
         }
+        GetNote { path } => Ok(ServerMessage::Note(read_note(state, &path).await?)),
+        GetNoteMetas { paths } => Ok(ServerMessage::GetNoteMetas(
+            read_note_metas(state, paths).await?,
+        )),
+        SearchContent { query, limit } => Ok(ServerMessage::SearchContent(
+            search_content(state, &query, limit).await?,
+        )),
+        FindInNotes { query } => Ok(ServerMessage::FindInNotes(
+            find_in_notes(state, &query).await?,
+        )),
+        ReplaceInNotes {
+            query,
+            replacement,
+            matches,
+        } => Ok(ServerMessage::ReplaceInNotes(
+            replace_in_notes(state, &query, &replacement, matches).await?,
+        )),
+        UndoReplace => Ok(ServerMessage::UndoReplace(undo_replace(state).await?)),
+        QueryNotes { query } => Ok(ServerMessage::QueryNotes(
+            query_notes(state, &NoteQuery::parse(&query)?).await?,
+        )),
+        QueryProperties { query } => Ok(ServerMessage::QueryProperties(
+            query_properties(state, query).await?,
+        )),
+        UpdateNote { path, note } => Ok(ServerMessage::UpdateNote(
+            write_note(state, &path, note).await?,
+        )),
         UpdatePath {
             current_path,
             new_title,
@@ -174,11 +381,41 @@ pub async fn handle_message(message: ClientMessage, state: &AppState) -> Result<
             let path = create_note(state, title).await?;
             Ok(ServerMessage::CreateNote(path))
         }
+        DeleteNote { path } => {
+            delete_note(state, &path).await?;
+            Ok(ServerMessage::DeleteNote)
+        }
+        ListTrash => Ok(ServerMessage::ListTrash(list_trash(state).await?)),
+        RestoreTrash { id } => Ok(ServerMessage::RestoreTrash(
+            restore_trash(state, &id).await?,
+        )),
+        PurgeTrash { max_age_days } => Ok(ServerMessage::PurgeTrash(
+            purge_trash(state, max_age_days).await?,
+        )),
+        ImportAttachment { name, bytes } => Ok(ServerMessage::ImportAttachment(
+            import_attachment(state, &name, bytes).await?,
+        )),
+        ListAttachments => Ok(ServerMessage::ListAttachments(
+            list_attachments(state).await?,
+        )),
+        ListNoteRevisions { path } => Ok(ServerMessage::ListNoteRevisions(
+            list_revisions(state, &path).await?,
+        )),
+        DiffNoteRevisions { path, from, to } => Ok(ServerMessage::DiffNoteRevisions(
+            diff_revisions(state, &path, &from, to.as_deref()).await?,
+        )),
+        RestoreNoteRevision {
+            path,
+            revision,
+            current_revision,
+        } => Ok(ServerMessage::RestoreNoteRevision(
+            restore_revision(state, &path, &revision, current_revision).await?,
+        )),
         CreatePalette {
             palette_key,
             filters,
         } => Ok(ServerMessage::CreatePalette(
-            create_palette(state, palette_key, filters).await?,
+            create_palette(state, palette_key, filters, window).await?,
         )),
         SearchPalette {
             id,
@@ -186,14 +423,19 @@ pub async fn handle_message(message: ClientMessage, state: &AppState) -> Result<
             start,
             end,
         } => Ok(ServerMessage::SearchPalette(
-            search_palette(state, id, search, start..end).await?,
+            search_palette(state, id, search, start..end, window).await?,
         )),
         DeletePalette { id } => {
             delete_palette(state, id).await;
             Ok(ServerMessage::DeletePalette)
         }
+        ChooseAction { action, path } => {
+            record_choice(state, &action, path.as_deref()).await?;
+            Ok(ServerMessage::ChooseAction)
+        }
+        ListSearchers => Ok(ServerMessage::ListSearchers(list_searchers(state).await)),
         CreateSuggester { suggester_source } => Ok(ServerMessage::CreateSuggester(
-            create_suggester(state, suggester_source).await?,
+            create_suggester(state, suggester_source, window).await?,
         )),
         SearchSuggester { id, search } => Ok(ServerMessage::SearchSuggester(
             search_suggester(state, id, search).await?,
@@ -225,12 +467,43 @@ pub async fn handle_message(message: ClientMessage, state: &AppState) -> Result<
         GetTagConfigs => Ok(ServerMessage::GetTagConfigs(
             read_meta(state, |holder| holder.meta().tag_configs.clone()).await?,
         )),
+        GetTagTree => Ok(ServerMessage::GetTagTree(get_tag_tree(state).await?)),
+        SetTagConfig { tag, config } => {
+            set_tag_config(state, &tag, config).await?;
+            Ok(ServerMessage::SetTagConfig)
+        }
+        ClearTagConfig { tag } => {
+            clear_tag_config(state, &tag).await?;
+            Ok(ServerMessage::ClearTagConfig)
+        }
+        GetTagDuplicates => Ok(ServerMessage::GetTagDuplicates(
+            find_duplicate_tags(state).await?,
+        )),
+        GetTagNormalization => Ok(ServerMessage::GetTagNormalization(
+            read_meta(state, |holder| holder.meta().tag_normalization).await?,
+        )),
+        SetTagNormalization { normalization } => {
+            set_tag_normalization(state, normalization).await?;
+            Ok(ServerMessage::SetTagNormalization)
+        }
+        RenameTag { from, to, dry_run } => Ok(ServerMessage::RenameTag(
+            rename_tag(state, &from, &to, dry_run).await?,
+        )),
+        MergeTag {
+            from,
+            into,
+            dry_run,
+        } => Ok(ServerMessage::MergeTag(
+            merge_tag(state, &from, &into, dry_run).await?,
+        )),
         Refresh => {
-            *state.meta.lock().await = None;
+            flush_meta(state).await?;
+            let vault = state.vault().await;
+            *vault.meta.lock().await = None;
+            *vault.actions.lock().await = None;
             let config_path = state.config_path.clone();
             *state.settings.lock().await =
                 tokio::task::spawn_blocking(move || read_settings_file(&config_path)).await??;
-            *state.actions.lock().await = None;
 
             Ok(ServerMessage::Refresh)
         }
@@ -242,7 +515,12 @@ pub async fn handle_message(message: ClientMessage, state: &AppState) -> Result<
             change,
             editor_view_id,
         } => {
+            let project_root = state
+                .store()
+                .await
+                .and_then(|store| store.root().map(Path::to_path_buf));
             let mut previewer = state.previewer.lock().await;
+            previewer.set_project_root(project_root);
             let res = previewer.update_source(change, editor_view_id);
             Ok(ServerMessage::PreviewerUpdateSource(res))
         }
@@ -261,8 +539,12 @@ pub enum ServerResult {
     Ok { message: ServerMessage },
     Err { error: String },
 }
-pub async fn handle_message_and_errors(message: ClientMessage, state: &AppState) -> ServerResult {
-    let response = handle_message(message, state).await;
+pub async fn handle_message_and_errors(
+    message: ClientMessage,
+    state: &AppState,
+    window: Option<&str>,
+) -> ServerResult {
+    let response = handle_message(message, state, window).await;
 
     match response {
         Ok(message) => ServerResult::Ok { message },
@@ -274,3 +556,75 @@ pub async fn handle_message_and_errors(message: ClientMessage, state: &AppState)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    /// Sends a message the way the frontend does and returns the json it gets back
+    async fn send(state: &AppState, message: Value) -> Value {
+        let message: ClientMessage = serde_json::from_value(message).unwrap();
+        serde_json::to_value(handle_message_and_errors(message, state, None).await).unwrap()
+    }
+
+    #[tokio::test]
+    async fn notes_round_trip_through_messages() {
+        let state = AppState::in_memory(&[]);
+        let created = send(
+            &state,
+            json!({ "type": "createNote", "data": { "title": "hello" } }),
+        )
+        .await;
+        assert_eq!(
+            created,
+            json!({ "type": "ok", "message": { "type": "createNote", "data": "hello.md" } })
+        );
+
+        let get_note = json!({ "type": "getNote", "data": { "path": "hello.md" } });
+        let read = send(&state, get_note.clone()).await;
+        let original = read["message"]["data"].clone();
+        assert_eq!(original["content"], "");
+
+        let mut note = original.clone();
+        note["content"] = json!("hello there");
+        let updated = send(
+            &state,
+            json!({ "type": "updateNote", "data": { "path": "hello.md", "note": note } }),
+        )
+        .await;
+        let result = &updated["message"]["data"];
+        assert_eq!(result["type"], "ok");
+
+        let read = send(&state, get_note).await;
+        assert_eq!(read["message"]["data"]["content"], "hello there");
+        assert_eq!(read["message"]["data"]["revision"], result["revision"]);
+        assert_eq!(read["message"]["data"]["meta"]["openCount"], 2);
+
+        // the note read before the update is stale now
+        let mut stale = original;
+        stale["content"] = json!("hello from before");
+        let rejected = send(
+            &state,
+            json!({ "type": "updateNote", "data": { "path": "hello.md", "note": stale } }),
+        )
+        .await;
+        assert_eq!(rejected["message"]["data"]["type"], "conflict");
+        assert_eq!(
+            rejected["message"]["data"]["current"]["content"],
+            "hello there"
+        );
+    }
+
+    #[tokio::test]
+    async fn errors_are_sent_back() {
+        let state = AppState::in_memory(&[]);
+        let result = send(
+            &state,
+            json!({ "type": "switchVault", "data": { "name": "missing" } }),
+        )
+        .await;
+        assert_eq!(result["type"], "err");
+    }
+}
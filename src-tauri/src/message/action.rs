@@ -10,7 +10,7 @@ use ts_rs::TS;
 
 use crate::{message::folder_manager::read, state::AppState};
 
-const ACTIONS_PATH: &str = "./brot_actions.toml";
+pub const ACTIONS_PATH: &str = "./brot_actions.toml";
 
 #[derive(Serialize, Deserialize, TS, Clone, Default)]
 #[ts(export)]
@@ -20,7 +20,7 @@ pub struct Actions {
     pub palettes: HashMap<String, HashMap<String, PartialActionGenerator>>,
 }
 
-#[derive(Serialize, PartialEq, Eq, Hash, TS, Clone)]
+#[derive(Serialize, PartialEq, Eq, Hash, TS, Clone, Debug)]
 #[ts(export)]
 #[serde(rename_all = "camelCase")]
 pub struct PartialAction {
@@ -35,6 +35,11 @@ pub struct PartialAction {
 pub struct PartialActionGenerator {
     pub key: String,
     pub args: Vec<String>,
+    /// a [`NoteQuery`](crate::message::note_query::NoteQuery) limiting which notes `$note_path`
+    /// and `$note_locater` generate actions for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub query: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, TS, Clone)]
@@ -68,7 +73,8 @@ pub async fn read_actions<T>(
     state: &AppState,
     mut function: impl FnMut(&Actions) -> T,
 ) -> Result<T> {
-    let mut guard = state.actions.lock().await;
+    let vault = state.vault().await;
+    let mut guard = vault.actions.lock().await;
     if let Some(ref actions) = *guard {
         Ok(function(actions))
     } else {
@@ -84,8 +90,36 @@ impl<'de> Deserialize<'de> for PartialActionGenerator {
     where
         D: Deserializer<'de>,
     {
-        let PartialAction { key, args } = PartialAction::deserialize(deserializer)?;
-        Ok(PartialActionGenerator { key, args })
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct GeneratorMap {
+            key: String,
+            #[serde(default)]
+            args: Vec<String>,
+            #[serde(default)]
+            query: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(
+            untagged,
+            expecting = "a string or a map with keys `key` and optional `args` and `query`"
+        )]
+        enum Generator {
+            Key(String),
+            Map(GeneratorMap),
+        }
+
+        Ok(match Generator::deserialize(deserializer)? {
+            Generator::Key(key) => PartialActionGenerator {
+                key,
+                args: Vec::new(),
+                query: None,
+            },
+            Generator::Map(GeneratorMap { key, args, query }) => {
+                PartialActionGenerator { key, args, query }
+            }
+        })
     }
 }
 
@@ -0,0 +1,84 @@
+use anyhow::{Result, bail};
+
+use crate::{
+    message::{
+        folder_manager::{free_path, read_dir, write_bytes},
+        title::sanitize_filename,
+    },
+    state::AppState,
+};
+
+/// Folder in the vault that images, PDFs and other files referenced by notes are kept in
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// Copies an attachment into the attachments folder, next to any existing attachment with the
+/// same name. Returns the path of the attachment in the vault.
+pub async fn import_attachment(state: &AppState, name: &str, bytes: Vec<u8>) -> Result<String> {
+    let name = sanitize_filename(name);
+    if name.is_empty() || name == "." || name == ".." {
+        bail!("invalid attachment name");
+    }
+    let path = free_path(state, &format!("{ATTACHMENTS_DIR}/{name}")).await?;
+    println!("importing attachment {:?}", path);
+    write_bytes(state, &path, bytes).await?;
+    Ok(path)
+}
+
+/// Lets the user pick a file and copies it into the vault the window shows. Files are only
+/// ever read from a path picked in the dialog, never from one a page sends. Returns None if the
+/// dialog was cancelled.
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub async fn import_attachment_file(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    pick_attachment_file(app, &state, window.label())
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(not(target_os = "android"))]
+async fn pick_attachment_file(
+    app: tauri::AppHandle,
+    state: &AppState,
+    window: &str,
+) -> Result<Option<String>> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let picked =
+        tokio::task::spawn_blocking(move || app.dialog().file().blocking_pick_file()).await?;
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let path = picked.into_path()?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let bytes = tokio::fs::read(&path).await?;
+    let state = state.for_window(Some(window)).await?;
+    Ok(Some(import_attachment(&state, &name, bytes).await?))
+}
+
+pub async fn list_attachments(state: &AppState) -> Result<Vec<String>> {
+    let prefix = format!("{ATTACHMENTS_DIR}/");
+    let mut attachments: Vec<String> = read_dir(state)
+        .await?
+        .into_iter()
+        .filter(|path| path.starts_with(&prefix))
+        .collect();
+    attachments.sort();
+    Ok(attachments)
+}
+
+/// Whether `path` is inside the attachments folder, so it may be served to the webview
+pub fn is_attachment_path(path: &str) -> bool {
+    path.strip_prefix(ATTACHMENTS_DIR)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .is_some_and(|rest| {
+            rest.split('/')
+                .all(|part| !part.is_empty() && !part.starts_with('.'))
+        })
+}
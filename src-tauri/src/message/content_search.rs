@@ -0,0 +1,406 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    message::meta::read_meta,
+    previewer::utf16::{Utf16Index, to_utf16_range},
+    state::AppState,
+};
+
+/// BM25 term frequency saturation
+const K1: f32 = 1.2;
+/// BM25 document length normalization
+const B: f32 = 0.75;
+
+/// How many tokens a snippet shows before and after the match it is about
+const SNIPPET_TOKENS_BEFORE: usize = 8;
+const SNIPPET_TOKENS_AFTER: usize = 16;
+
+/// Most match ranges returned for a single note
+const MAX_RANGES: usize = 100;
+
+const DEFAULT_LIMIT: usize = 20;
+
+/// Inverted index over the contents of every note in a vault
+#[derive(Default)]
+pub struct SearchIndex {
+    notes: HashMap<String, IndexedNote>,
+    /// the notes containing each term, with the positions of the term in the note's tokens
+    postings: HashMap<String, HashMap<String, Vec<u32>>>,
+    total_tokens: usize,
+}
+
+struct IndexedNote {
+    content: String,
+    /// byte range of each token in the content
+    tokens: Vec<Range<usize>>,
+}
+
+/// Splits text into lowercased words, along with their byte ranges
+fn tokenize(text: &str) -> Vec<(String, Range<usize>)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        if c.is_alphanumeric() {
+            start.get_or_insert(i);
+        } else if let Some(start) = start.take() {
+            tokens.push((text[start..i].to_lowercase(), start..i));
+        }
+    }
+    if let Some(start) = start {
+        tokens.push((text[start..].to_lowercase(), start..text.len()));
+    }
+    tokens
+}
+
+enum Clause {
+    Term(String),
+    Prefix(String),
+    Phrase(Vec<String>),
+}
+
+/// Parses a query into clauses that all have to match. `"quoted words"` are phrases, a word
+/// ending in `*` matches every term it is a prefix of, and other words match themselves.
+fn parse_query(query: &str) -> Vec<Clause> {
+    let mut clauses = Vec::new();
+    for (i, part) in query.split('"').enumerate() {
+        // every other part is inside quotes
+        if i % 2 == 1 {
+            let mut terms: Vec<String> = tokenize(part).into_iter().map(|(t, _)| t).collect();
+            match terms.len() {
+                0 => {}
+                1 => clauses.push(Clause::Term(terms.remove(0))),
+                _ => clauses.push(Clause::Phrase(terms)),
+            }
+            continue;
+        }
+        for word in part.split_whitespace() {
+            let mut terms: Vec<String> = tokenize(word).into_iter().map(|(t, _)| t).collect();
+            let prefix = word.ends_with('*').then(|| terms.pop()).flatten();
+            clauses.extend(terms.into_iter().map(Clause::Term));
+            clauses.extend(prefix.map(Clause::Prefix));
+        }
+    }
+    clauses
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentMatch {
+    pub path: String,
+    /// BM25 score, higher is better
+    pub score: f32,
+    /// the text around the first match, on a single line
+    pub snippet: String,
+    /// ranges of the matches within the snippet
+    pub snippet_highlights: Vec<Range<Utf16Index>>,
+    /// ranges of the matches within the note, for the editor to jump to
+    pub ranges: Vec<Range<Utf16Index>>,
+}
+
+impl SearchIndex {
+    pub fn insert(&mut self, path: &str, content: &str) {
+        self.remove(path);
+        let tokens = tokenize(content);
+        for (position, (term, _)) in tokens.iter().enumerate() {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .entry(path.to_owned())
+                .or_default()
+                .push(position as u32);
+        }
+        self.total_tokens += tokens.len();
+        self.notes.insert(
+            path.to_owned(),
+            IndexedNote {
+                content: content.to_owned(),
+                tokens: tokens.into_iter().map(|(_, range)| range).collect(),
+            },
+        );
+    }
+
+    pub fn remove(&mut self, path: &str) {
+        let Some(note) = self.notes.remove(path) else {
+            return;
+        };
+        self.total_tokens -= note.tokens.len();
+        let terms: HashSet<String> = note
+            .tokens
+            .iter()
+            .map(|range| note.content[range.clone()].to_lowercase())
+            .collect();
+        for term in terms {
+            if let Some(paths) = self.postings.get_mut(&term) {
+                paths.remove(path);
+                if paths.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    /// Removes every note `keep` returns false for
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        let removed: Vec<String> = self
+            .notes
+            .keys()
+            .filter(|path| !keep(path))
+            .cloned()
+            .collect();
+        for path in removed {
+            self.remove(&path);
+        }
+    }
+
+    /// Spans of token positions the clause matches, by note
+    fn clause_matches(&self, clause: &Clause) -> HashMap<&str, Vec<Range<u32>>> {
+        let mut matches: HashMap<&str, Vec<Range<u32>>> = HashMap::new();
+        let mut add_term = |paths: &HashMap<String, Vec<u32>>| {
+            for (path, positions) in paths {
+                let spans = matches.entry(path.as_str()).or_default();
+                spans.extend(positions.iter().map(|&p| p..p + 1));
+            }
+        };
+        match clause {
+            Clause::Term(term) => {
+                if let Some(paths) = self.postings.get(term) {
+                    add_term(paths);
+                }
+            }
+            Clause::Prefix(prefix) => {
+                for (_, paths) in self
+                    .postings
+                    .iter()
+                    .filter(|(term, _)| term.starts_with(prefix.as_str()))
+                {
+                    add_term(paths);
+                }
+            }
+            Clause::Phrase(terms) => {
+                let Some(postings) = terms
+                    .iter()
+                    .map(|term| self.postings.get(term))
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    return matches;
+                };
+                for (path, starts) in postings[0] {
+                    let spans: Vec<Range<u32>> = starts
+                        .iter()
+                        .filter(|&&start| {
+                            postings[1..].iter().enumerate().all(|(i, paths)| {
+                                paths.get(path).is_some_and(|positions| {
+                                    positions.binary_search(&(start + i as u32 + 1)).is_ok()
+                                })
+                            })
+                        })
+                        .map(|&start| start..start + terms.len() as u32)
+                        .collect();
+                    if !spans.is_empty() {
+                        matches.insert(path.as_str(), spans);
+                    }
+                }
+            }
+        }
+        matches
+    }
+
+    /// Ranks the notes matching every clause of `query` with BM25, best first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<ContentMatch> {
+        let clauses = parse_query(query);
+        if clauses.is_empty() || self.notes.is_empty() {
+            return Vec::new();
+        }
+        let note_count = self.notes.len() as f32;
+        let average_len = self.total_tokens as f32 / note_count;
+
+        let mut results: Option<HashMap<&str, (f32, Vec<Range<u32>>)>> = None;
+        for clause in &clauses {
+            let matches = self.clause_matches(clause);
+            let df = matches.len() as f32;
+            let idf = (1.0 + (note_count - df + 0.5) / (df + 0.5)).ln();
+            let mut scored = HashMap::new();
+            for (path, spans) in matches {
+                // every clause has to match, so notes missed by an earlier one are skipped
+                let (score, mut all_spans) = match &mut results {
+                    Some(results) => match results.remove(path) {
+                        Some(result) => result,
+                        None => continue,
+                    },
+                    None => (0.0, Vec::new()),
+                };
+                let len = self.notes[path].tokens.len() as f32;
+                let tf = spans.len() as f32;
+                let score =
+                    score + idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * len / average_len));
+                all_spans.extend(spans);
+                scored.insert(path, (score, all_spans));
+            }
+            results = Some(scored);
+        }
+
+        let mut results: Vec<_> = results.unwrap_or_default().into_iter().collect();
+        results.sort_by(|(a_path, (a, _)), (b_path, (b, _))| {
+            b.total_cmp(a).then_with(|| a_path.cmp(b_path))
+        });
+        results
+            .into_iter()
+            .take(limit)
+            .map(|(path, (score, mut spans))| {
+                spans.sort_by_key(|span| span.start);
+                self.content_match(path, score, &spans)
+            })
+            .collect()
+    }
+
+    fn content_match(&self, path: &str, score: f32, spans: &[Range<u32>]) -> ContentMatch {
+        let note = &self.notes[path];
+        let byte_range = |span: &Range<u32>| {
+            note.tokens[span.start as usize].start..note.tokens[span.end as usize - 1].end
+        };
+
+        let first = &spans[0];
+        let start_token = (first.start as usize).saturating_sub(SNIPPET_TOKENS_BEFORE);
+        let end_token = (first.end as usize + SNIPPET_TOKENS_AFTER).min(note.tokens.len());
+        let snippet_range = note.tokens[start_token].start..note.tokens[end_token - 1].end;
+        // newlines and spaces are the same length in utf-16, so the highlights stay valid
+        let snippet = note.content[snippet_range.clone()].replace(['\n', '\r', '\t'], " ");
+        let snippet_highlights = spans
+            .iter()
+            .map(byte_range)
+            .filter(|range| snippet_range.start <= range.start && range.end <= snippet_range.end)
+            .filter_map(|range| {
+                let relative = range.start - snippet_range.start..range.end - snippet_range.start;
+                to_utf16_range(&snippet, &relative)
+            })
+            .collect();
+        let ranges = spans
+            .iter()
+            .take(MAX_RANGES)
+            .filter_map(|span| to_utf16_range(&note.content, &byte_range(span)))
+            .collect();
+
+        ContentMatch {
+            path: path.to_owned(),
+            score,
+            snippet,
+            snippet_highlights,
+            ranges,
+        }
+    }
+}
+
+/// Searches the contents of every note, returning at most `limit` notes
+pub async fn search_content(
+    state: &AppState,
+    query: &str,
+    limit: Option<u32>,
+) -> Result<Vec<ContentMatch>> {
+    let limit = limit.map_or(DEFAULT_LIMIT, |limit| limit as usize);
+    read_meta(state, |holder| holder.search_index().search(query, limit)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::note::{Note, delete_note, read_note, write_note};
+
+    fn index(notes: &[(&str, &str)]) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for (path, content) in notes {
+            index.insert(path, content);
+        }
+        index
+    }
+
+    fn paths(index: &SearchIndex, query: &str) -> Vec<String> {
+        index
+            .search(query, DEFAULT_LIMIT)
+            .into_iter()
+            .map(|result| result.path)
+            .collect()
+    }
+
+    #[test]
+    fn more_frequent_terms_rank_higher() {
+        let index = index(&[
+            ("once.md", "rust and a few other words about cooking"),
+            ("often.md", "rust rust rust guide"),
+            ("never.md", "nothing to see here"),
+        ]);
+        assert_eq!(paths(&index, "rust"), ["often.md", "once.md"]);
+        // every word has to match
+        assert_eq!(paths(&index, "rust cooking"), ["once.md"]);
+    }
+
+    #[test]
+    fn phrases_need_adjacent_words_and_prefixes_need_a_star() {
+        let index = index(&[
+            ("in_order.md", "the quick brown fox"),
+            ("swapped.md", "brown quick fox"),
+        ]);
+        assert_eq!(paths(&index, r#""quick brown""#), ["in_order.md"]);
+        let mut prefixed = paths(&index, "qui*");
+        prefixed.sort();
+        assert_eq!(prefixed, ["in_order.md", "swapped.md"]);
+        assert!(paths(&index, "qui").is_empty());
+
+        let result = &index.search(r#""quick brown""#, DEFAULT_LIMIT)[0];
+        assert_eq!(result.snippet, "the quick brown fox");
+        let highlighted: Vec<&str> = result
+            .snippet_highlights
+            .iter()
+            .map(|range| {
+                let start = range.start.to_utf8_index(&result.snippet).unwrap();
+                let end = range.end.to_utf8_index(&result.snippet).unwrap();
+                &result.snippet[start..end]
+            })
+            .collect();
+        assert_eq!(highlighted, ["quick brown"]);
+    }
+
+    #[test]
+    fn reinserting_and_removing_drops_old_postings() {
+        let mut index = index(&[("a.md", "old words")]);
+        index.insert("a.md", "new words");
+        assert!(paths(&index, "old").is_empty());
+        assert_eq!(paths(&index, "new"), ["a.md"]);
+        assert_eq!(index.total_tokens, 2);
+
+        index.remove("a.md");
+        assert!(paths(&index, "words").is_empty());
+        assert!(index.postings.is_empty());
+        assert_eq!(index.total_tokens, 0);
+    }
+
+    async fn search(state: &AppState, query: &str) -> Vec<String> {
+        let results = search_content(state, query, None).await.unwrap();
+        results.into_iter().map(|result| result.path).collect()
+    }
+
+    #[tokio::test]
+    async fn writing_and_deleting_notes_updates_the_index() {
+        let state = AppState::in_memory(&[("a.md", "old text")]);
+        assert_eq!(search(&state, "old").await, ["a.md"]);
+
+        let note = read_note(&state, "a.md").await.unwrap().unwrap();
+        let note = Note {
+            content: "new text".to_owned(),
+            ..note
+        };
+        write_note(&state, "a.md", note).await.unwrap();
+        assert!(search(&state, "old").await.is_empty());
+        assert_eq!(search(&state, "new").await, ["a.md"]);
+
+        delete_note(&state, "a.md").await.unwrap();
+        assert!(search(&state, "text").await.is_empty());
+    }
+}
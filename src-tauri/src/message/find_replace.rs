@@ -0,0 +1,343 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use anyhow::{Result, anyhow, bail};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    message::{
+        folder_manager::read,
+        history::commit_pending,
+        meta::{read_meta, read_note_meta},
+        note::{Note, WriteNoteResult, content_revision, write_note},
+    },
+    previewer::utf16::{Utf16Index, to_utf8_range, to_utf16_range},
+    state::AppState,
+};
+
+/// Most characters of a line shown on either side of a match
+const CONTEXT_CHARS: usize = 80;
+
+/// Most matches returned for a single search, across all notes
+const MAX_MATCHES: usize = 10_000;
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct FindQuery {
+    pub pattern: String,
+    /// treat the pattern as a regular expression rather than literal text. The replacement can
+    /// then refer to groups like `$1`.
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+impl FindQuery {
+    fn build(&self) -> Result<Regex> {
+        if self.pattern.is_empty() {
+            bail!("nothing to find");
+        }
+        let pattern = if self.regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+        Ok(RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .multi_line(true)
+            .build()?)
+    }
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct FindMatch {
+    pub path: String,
+    /// revision of the note the match was found in, so a replace can tell the note changed
+    /// since
+    pub revision: String,
+    /// line the match starts on, counting from 1
+    pub line: u32,
+    /// the text of that line around the match
+    pub context: String,
+    /// range of the match within the context
+    pub context_range: Range<Utf16Index>,
+    /// range of the match within the note
+    pub range: Range<Utf16Index>,
+}
+
+/// The part of the line around `range` that a match is shown with
+fn context_range(content: &str, range: &Range<usize>) -> Range<usize> {
+    let line_start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let mut line_end = content[range.end..]
+        .find('\n')
+        .map_or(content.len(), |i| range.end + i);
+    if line_end > range.end && content[..line_end].ends_with('\r') {
+        line_end -= 1;
+    }
+    let start = content[line_start..range.start]
+        .char_indices()
+        .rev()
+        .nth(CONTEXT_CHARS - 1)
+        .map_or(line_start, |(i, _)| line_start + i);
+    let end = content[range.end..line_end]
+        .char_indices()
+        .nth(CONTEXT_CHARS)
+        .map_or(line_end, |(i, _)| range.end + i);
+    start..end
+}
+
+fn find_matches(path: &str, content: &str, regex: &Regex) -> Vec<FindMatch> {
+    let revision = content_revision(content);
+    let mut line = 1;
+    let mut counted = 0;
+    regex
+        .find_iter(content)
+        .filter(|found| !found.is_empty())
+        .filter_map(|found| {
+            let range = found.range();
+            line += content[counted..range.start].matches('\n').count() as u32;
+            counted = range.start;
+            let context = context_range(content, &range);
+            let text = &content[context.clone()];
+            let relative = range.start - context.start..range.end - context.start;
+            Some(FindMatch {
+                path: path.to_owned(),
+                revision: revision.clone(),
+                line,
+                context: text.to_owned(),
+                context_range: to_utf16_range(text, &relative)?,
+                range: to_utf16_range(content, &range)?,
+            })
+        })
+        .collect()
+}
+
+/// Finds every match of `query` in the contents of every note, sorted by note and position
+pub async fn find_in_notes(state: &AppState, query: &FindQuery) -> Result<Vec<FindMatch>> {
+    let regex = query.build()?;
+    let mut paths: Vec<String> = read_meta(state, |holder| {
+        holder.meta().notes.keys().cloned().collect()
+    })
+    .await?;
+    paths.sort();
+    let mut matches = Vec::new();
+    for path in paths {
+        let Some(content) = read(state, &path).await? else {
+            continue;
+        };
+        matches.extend(find_matches(&path, &content, &regex));
+        if matches.len() >= MAX_MATCHES {
+            matches.truncate(MAX_MATCHES);
+            break;
+        }
+    }
+    Ok(matches)
+}
+
+/// A change to the content of a note, kept so it can be undone
+#[derive(Clone, Debug)]
+pub struct NoteEdit {
+    pub path: String,
+    pub before: String,
+    pub after: String,
+}
+
+impl NoteEdit {
+    fn reversed(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            before: self.after.clone(),
+            after: self.before.clone(),
+        }
+    }
+}
+
+/// Puts back the content a note had before `edit` was written, unless it changed since
+async fn roll_back(state: &AppState, edit: &NoteEdit) -> Result<()> {
+    let meta = read_note_meta(state, &edit.path).await?.unwrap_or_default();
+    let note = Note {
+        content: edit.before.clone(),
+        meta,
+        revision: Some(content_revision(&edit.after)),
+    };
+    match write_note(state, &edit.path, note).await? {
+        WriteNoteResult::Ok { .. } => Ok(()),
+        WriteNoteResult::Conflict { .. } => bail!("it changed again while replacing"),
+    }
+}
+
+/// Writes every edit through [`write_note`], or none of them. A note that changed since its
+/// edit was made stops the writing, and the notes already written are put back.
+async fn apply_edits(state: &AppState, edits: &[NoteEdit]) -> Result<()> {
+    // the edits get a history commit of their own
+    commit_pending(state).await?;
+    let mut written: Vec<&NoteEdit> = Vec::new();
+    for edit in edits {
+        let meta = read_note_meta(state, &edit.path).await?.unwrap_or_default();
+        let note = Note {
+            content: edit.after.clone(),
+            meta,
+            revision: Some(content_revision(&edit.before)),
+        };
+        let error = match write_note(state, &edit.path, note).await {
+            Ok(WriteNoteResult::Ok { .. }) => {
+                written.push(edit);
+                continue;
+            }
+            Ok(WriteNoteResult::Conflict { .. }) => {
+                anyhow!(
+                    "{:?} changed while replacing, so nothing was replaced",
+                    edit.path
+                )
+            }
+            Err(err) => err,
+        };
+        let mut failures = Vec::new();
+        for edit in written.into_iter().rev() {
+            if let Err(err) = roll_back(state, edit).await {
+                failures.push(format!("{:?}: {err}", edit.path));
+            }
+        }
+        if failures.is_empty() {
+            return Err(error);
+        }
+        bail!(
+            "{error}, and these notes couldn't be put back: {}",
+            failures.join(", ")
+        );
+    }
+    commit_pending(state).await?;
+    Ok(())
+}
+
+/// Replaces the given matches of `query` with `replacement`, across all their notes at once.
+/// Fails without changing anything if a note changed since it was searched. Returns the paths
+/// of the changed notes.
+pub async fn replace_in_notes(
+    state: &AppState,
+    query: &FindQuery,
+    replacement: &str,
+    matches: Vec<FindMatch>,
+) -> Result<Vec<String>> {
+    let regex = query.build()?;
+    let mut by_path: BTreeMap<String, Vec<FindMatch>> = BTreeMap::new();
+    for found in matches {
+        by_path.entry(found.path.clone()).or_default().push(found);
+    }
+
+    // every note is checked before anything is written
+    let mut edits = Vec::new();
+    for (path, matches) in by_path {
+        let Some(content) = read(state, &path).await? else {
+            bail!("{path:?} no longer exists, search again");
+        };
+        let revision = content_revision(&content);
+        if matches.iter().any(|found| found.revision != revision) {
+            bail!("{path:?} changed since it was searched, search again");
+        }
+        let mut ranges = matches
+            .iter()
+            .map(|found| {
+                to_utf8_range(&content, &found.range)
+                    .ok_or_else(|| anyhow!("invalid match range in {path:?}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        ranges.sort_by_key(|range| (range.start, range.end));
+        ranges.dedup();
+        if ranges.windows(2).any(|pair| pair[0].end > pair[1].start) {
+            bail!("matches in {path:?} overlap");
+        }
+
+        let mut after = String::with_capacity(content.len());
+        let mut last = 0;
+        for range in ranges {
+            let captures = regex
+                .captures_at(&content, range.start)
+                .filter(|captures| captures.get(0).is_some_and(|m| m.range() == range))
+                .ok_or_else(|| anyhow!("a match in {path:?} doesn't match anymore"))?;
+            after.push_str(&content[last..range.start]);
+            if query.regex {
+                captures.expand(replacement, &mut after);
+            } else {
+                after.push_str(replacement);
+            }
+            last = range.end;
+        }
+        after.push_str(&content[last..]);
+        if after != content {
+            edits.push(NoteEdit {
+                path,
+                before: content,
+                after,
+            });
+        }
+    }
+
+    println!("replacing {:?} in {} notes", query.pattern, edits.len());
+    apply_edits(state, &edits).await?;
+    let paths = edits.iter().map(|edit| edit.path.clone()).collect();
+    // a replace that changed nothing keeps the previous one undoable
+    if !edits.is_empty() {
+        *state.vault().await.last_replace.lock().await = Some(edits);
+    }
+    Ok(paths)
+}
+
+/// Puts back every note the last replace changed, as long as none of them changed since.
+/// Returns the paths of the restored notes.
+pub async fn undo_replace(state: &AppState) -> Result<Vec<String>> {
+    let vault = state.vault().await;
+    let mut last_replace = vault.last_replace.lock().await;
+    let Some(edits) = last_replace.as_ref() else {
+        bail!("there is no replace to undo");
+    };
+    let reversed: Vec<NoteEdit> = edits.iter().rev().map(NoteEdit::reversed).collect();
+    apply_edits(state, &reversed).await?;
+    *last_replace = None;
+    Ok(reversed.into_iter().map(|edit| edit.path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(pattern: &str) -> FindQuery {
+        FindQuery {
+            pattern: pattern.to_owned(),
+            regex: false,
+            case_sensitive: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn replacing_without_changes_keeps_the_undo() {
+        let state = AppState::in_memory(&[("a.md", "one two"), ("b.md", "two")]);
+        let matches = find_in_notes(&state, &query("two")).await.unwrap();
+        let paths = replace_in_notes(&state, &query("two"), "three", matches)
+            .await
+            .unwrap();
+        assert_eq!(paths, ["a.md", "b.md"]);
+        assert_eq!(
+            read(&state, "a.md").await.unwrap().as_deref(),
+            Some("one three")
+        );
+
+        let matches = find_in_notes(&state, &query("three")).await.unwrap();
+        let paths = replace_in_notes(&state, &query("three"), "three", matches)
+            .await
+            .unwrap();
+        assert!(paths.is_empty());
+
+        undo_replace(&state).await.unwrap();
+        assert_eq!(
+            read(&state, "a.md").await.unwrap().as_deref(),
+            Some("one two")
+        );
+        assert_eq!(read(&state, "b.md").await.unwrap().as_deref(), Some("two"));
+    }
+}
@@ -1,240 +1,153 @@
-use std::path::PathBuf;
-
-use anyhow::anyhow;
 use anyhow::Result;
-use tauri::App;
-use tauri_plugin_android_fs::Entry;
-use tauri_plugin_android_fs::{AndroidFsExt, FileUri, PersistableAccessMode, PrivateDir};
-use tokio::fs;
-
-use crate::state::AppState;
-
-#[allow(unused)]
-fn downloads_subfolder_uri(app: &mut App) -> Result<FileUri> {
-    let api = app.android_fs();
-
-    let storage_key = format!("subfolder_uri");
-
-    let stored_uri = api
-        .private_storage()
-        .read_to_string(PrivateDir::Data, &storage_key)
-        .ok()
-        .and_then(|s| FileUri::from_str(&s).ok());
-
-    if let Some(uri) = stored_uri {
-        if api.check_persisted_uri_permission(&uri, PersistableAccessMode::ReadAndWrite)? {
-            return Ok(uri);
-        }
-    }
-
-    let selected_uri = api.show_manage_dir_dialog(None)?;
-
-    let Some(uri) = selected_uri else {
-        return Err(anyhow!("User cancelled folder selection"));
+use sha2::{Digest, Sha256};
+
+use crate::{
+    message::store::{backup_path, tmp_path, FileMetadata, NoteStore},
+    state::AppState,
+};
+
+/// Runs `function` on the state's store off the async runtime, or returns `default` if there
+/// is no notes folder to store things in
+async fn with_store<T: Send + 'static>(
+    state: &AppState,
+    default: T,
+    function: impl FnOnce(&dyn NoteStore) -> Result<T> + Send + 'static,
+) -> Result<T> {
+    let Some(store) = state.store().await else {
+        return Ok(default);
     };
+    tokio::task::spawn_blocking(move || function(store.as_ref())).await?
+}
 
-    api.take_persistable_uri_permission(&uri)?;
-
-    api.private_storage()
-        .write(PrivateDir::Data, &storage_key, uri.to_string()?.as_bytes())?;
+pub async fn read(state: &AppState, path: &str) -> Result<Option<String>> {
+    let path = path.to_owned();
+    with_store(state, None, move |store| store.read(&path)).await
+}
 
-    Ok(uri)
+pub async fn read_bytes(state: &AppState, path: &str) -> Result<Option<Vec<u8>>> {
+    let path = path.to_owned();
+    with_store(state, None, move |store| store.read_bytes(&path)).await
 }
 
-#[derive(Debug, Clone)]
-pub enum FolderManager {
-    Android { uri: FileUri },
-    Normal,
+/// Hash of a file's contents, used to recognise changes that brot made itself
+pub fn content_hash(contents: &str) -> u64 {
+    // revisions are sent to the frontend and compared later, so the hash has to be the same
+    // across rust releases and platforms, which the standard library's hashers aren't
+    let digest = Sha256::digest(contents.as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().unwrap())
 }
 
-impl FolderManager {
-    #[allow(unused)]
-    pub fn new(app: &mut App) -> Result<Self> {
-        #[cfg(not(target_os = "android"))]
-        {
-            Ok(Self::Normal)
-        }
+pub async fn write(state: &AppState, path: &str, contents: String) -> Result<()> {
+    state
+        .recent_writes
+        .lock()
+        .await
+        .insert(path.to_owned(), Some(content_hash(&contents)));
+    let path = path.to_owned();
+    with_store(state, (), move |store| store.write(&path, &contents)).await
+}
 
-        #[cfg(target_os = "android")]
-        {
-            Ok(Self::Android {
-                uri: downloads_subfolder_uri(app)?,
-            })
-        }
-    }
+/// Writes a binary file. Unlike [`write`] it isn't recorded in `recent_writes`, since the
+/// watcher only reports changes to notes.
+pub async fn write_bytes(state: &AppState, path: &str, contents: Vec<u8>) -> Result<()> {
+    let path = path.to_owned();
+    with_store(state, (), move |store| store.write_bytes(&path, &contents)).await
 }
 
-pub async fn read(state: &AppState, path: &str) -> Result<Option<String>> {
-    match state.folder_manager {
-        FolderManager::Normal => {
-            let Some(path) = get_folder_path(state, path).await else {
-                return Ok(None);
-            };
-            match fs::read_to_string(path).await {
-                Ok(contents) => Ok(Some(contents)),
-                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-                Err(e) => Err(e.into()),
-            }
-        }
-        FolderManager::Android { ref uri } => {
-            if !file_exists(state, path).await? {
-                return Ok(None);
-            }
-            let uri = uri.clone();
-            let app = state.handle.clone();
-            let path = path.to_owned();
-            tokio::task::spawn_blocking(move || {
-                let api = app.android_fs();
-                match api.resolve_uri(&uri, &path) {
-                    Ok(file_uri) => Ok(Some(api.read_to_string(&file_uri)?)),
-                    Err(tauri_plugin_android_fs::Error::Io(e))
-                        if e.kind() == std::io::ErrorKind::NotFound =>
-                    {
-                        Ok(None)
-                    }
-                    Err(e) => Err(e.into()),
-                }
-            })
-            .await?
-        }
+/// Writes a json file in the notes folder, first keeping its current contents as a backup if
+/// they are still valid json
+pub async fn write_with_backup(state: &AppState, path: &str, contents: String) -> Result<()> {
+    if let Some(current) = read(state, path).await?
+        && serde_json::from_str::<serde_json::Value>(&current).is_ok()
+    {
+        write(state, &backup_path(path), current).await?;
     }
+    write(state, path, contents).await
 }
 
-pub async fn write(state: &AppState, path: &str, contents: String) -> Result<()> {
-    match state.folder_manager {
-        FolderManager::Normal => {
-            let Some(path) = get_folder_path(state, path).await else {
-                return Ok(());
-            };
-            fs::write(PathBuf::from(&path), contents).await?;
-            Ok(())
-        }
-        FolderManager::Android { ref uri } => {
-            if !file_exists(state, path).await? {
-                let uri = uri.clone();
-                let path = path.to_owned();
-                let app = state.handle.clone();
-                tokio::task::spawn_blocking(move || -> Result<()> {
-                    let api = app.android_fs();
-                    api.create_file(&uri, path, None)?;
-                    Ok(())
-                })
-                .await??;
-            }
-            let uri = uri.clone();
-            let path = path.to_owned();
-            let app = state.handle.clone();
-            tokio::task::spawn_blocking(move || {
-                let api = app.android_fs();
-                let file_uri = api.resolve_uri(&uri, path)?;
-                api.write(&file_uri, contents.as_bytes())?;
-                Ok(())
-            })
-            .await?
+/// Reads and parses a json file in the notes folder, falling back to the leftover temporary
+/// file and then the backup if it is missing or corrupt. Returns None if none of them exist.
+pub async fn read_with_backup<T>(
+    state: &AppState,
+    path: &str,
+    parse: impl Fn(&str) -> Result<T>,
+) -> Result<Option<T>> {
+    let Some(contents) = read(state, path).await? else {
+        return Ok(None);
+    };
+    let err = match parse(&contents) {
+        Ok(value) => return Ok(Some(value)),
+        Err(err) => err,
+    };
+    for fallback in [tmp_path(path), backup_path(path)] {
+        if let Some(contents) = read(state, &fallback).await?
+            && let Ok(value) = parse(&contents)
+        {
+            println!("{path:?} is corrupt ({err}), using {fallback:?} instead");
+            return Ok(Some(value));
         }
     }
+    Err(err)
 }
 
 pub async fn remove_file(state: &AppState, path: &str) -> Result<()> {
-    match state.folder_manager {
-        FolderManager::Normal => {
-            let Some(path) = get_folder_path(state, path).await else {
-                return Ok(());
-            };
-            fs::remove_file(path).await?;
-            Ok(())
-        }
-        FolderManager::Android { ref uri } => {
-            let uri = uri.clone();
-            let path = path.to_owned();
-            let app = state.handle.clone();
-            tokio::task::spawn_blocking(move || {
-                let api = app.android_fs();
-                let file_uri = api.resolve_uri(&uri, path)?;
-                api.remove_file(&file_uri)?;
-                Ok(())
-            })
-            .await?
-        }
+    state
+        .recent_writes
+        .lock()
+        .await
+        .insert(path.to_owned(), None);
+    let path = path.to_owned();
+    with_store(state, (), move |store| store.remove(&path)).await
+}
+
+pub async fn rename_file(state: &AppState, from: &str, to: &str) -> Result<()> {
+    {
+        let mut recent_writes = state.recent_writes.lock().await;
+        let hash = recent_writes.remove(from).flatten();
+        recent_writes.insert(from.to_owned(), None);
+        recent_writes.insert(to.to_owned(), hash);
     }
+    let from = from.to_owned();
+    let to = to.to_owned();
+    with_store(state, (), move |store| store.rename(&from, &to)).await
 }
 
 pub async fn file_exists(state: &AppState, path: &str) -> Result<bool> {
-    match state.folder_manager {
-        FolderManager::Normal => {
-            let Some(path) = get_folder_path(state, path).await else {
-                return Ok(false);
-            };
-            Ok(fs::try_exists(path).await?)
-        }
-        FolderManager::Android { ref uri } => {
-            let uri = uri.clone();
-            let path = path.to_owned();
-            let app = state.handle.clone();
-            tokio::task::spawn_blocking(move || {
-                let api = app.android_fs();
-                match api.resolve_uri(&uri, &path) {
-                    Ok(uri) => match api.get_mime_type(&uri) {
-                        Ok(Some(_)) => Ok(true),
-                        Ok(None) => Ok(false),
-                        Err(tauri_plugin_android_fs::Error::Io(e))
-                            if e.kind() == std::io::ErrorKind::NotFound =>
-                        {
-                            Ok(false)
-                        }
-                        // TODO find better solution for this
-                        Err(tauri_plugin_android_fs::Error::PluginInvoke(message))
-                            if message.contains("java.io.FileNotFoundException") =>
-                        {
-                            Ok(false)
-                        }
-
-                        Err(err) => Err(err.into()),
-                    },
-                    Err(err) => Err(err.into()),
-                }
-            })
-            .await?
-        }
-    }
+    let path = path.to_owned();
+    with_store(state, false, move |store| store.exists(&path)).await
 }
 
-async fn get_folder_path(state: &AppState, path: &str) -> Option<PathBuf> {
-    Some(PathBuf::from(state.settings.lock().await.notes_path.clone()?).join(path))
+pub async fn file_metadata(state: &AppState, path: &str) -> Result<Option<FileMetadata>> {
+    let path = path.to_owned();
+    with_store(state, None, move |store| store.metadata(&path)).await
 }
 
-pub async fn read_dir(state: &AppState) -> Result<Vec<String>> {
-    match state.folder_manager {
-        FolderManager::Normal => {
-            let Some(path) = get_folder_path(state, "").await else {
-                return Ok(Vec::new());
-            };
-            let mut entries = fs::read_dir(&path).await?;
-            let mut paths = Vec::new();
-            while let Some(entry) = entries.next_entry().await? {
-                paths.push(
-                    entry
-                        .file_name()
-                        .into_string()
-                        .expect("invalid entry string"),
-                );
-            }
-            Ok(paths)
-        }
-        FolderManager::Android { ref uri } => {
-            let uri = uri.clone();
-            let app = state.handle.clone();
-            tokio::task::spawn_blocking(move || {
-                let api = app.android_fs();
-                Ok(api
-                    .read_dir(&uri)?
-                    .map(|entry| match entry {
-                        Entry::File { name, .. } => name,
-                        Entry::Dir { name, .. } => name,
-                    })
-                    .collect())
-            })
-            .await?
+/// Returns `path` if nothing exists there yet, or else the first free `name_n.ext` next to it
+pub async fn free_path(state: &AppState, path: &str) -> Result<String> {
+    if !file_exists(state, path).await? {
+        return Ok(path.to_owned());
+    }
+    let (stem, extension) = match path.rsplit_once('.') {
+        Some((stem, extension)) if !extension.contains('/') => (stem, format!(".{extension}")),
+        _ => (path, String::new()),
+    };
+    for n in 2.. {
+        let candidate = format!("{stem}_{n}{extension}");
+        if !file_exists(state, &candidate).await? {
+            return Ok(candidate);
         }
     }
+    unreachable!()
+}
+
+/// Recursively lists every file in the notes folder as a `/` separated path relative to it
+pub async fn read_dir(state: &AppState) -> Result<Vec<String>> {
+    let ignored = state
+        .settings
+        .lock()
+        .await
+        .ignored_paths
+        .clone()
+        .unwrap_or_default();
+    with_store(state, Vec::new(), move |store| store.list(&ignored)).await
 }
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    message::{
+        action::PartialAction,
+        meta::{now_secs, write_meta},
+        palette_action::PaletteAction,
+        searcher::RankingPolicy,
+    },
+    state::AppState,
+};
+
+/// How long it takes for a use to count half as much
+const HALF_LIFE_SECS: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+
+/// Usage scores that have decayed below this are forgotten
+const MIN_SCORE: f64 = 0.01;
+
+/// How much frecency counts compared to how well something matched the search
+const FRECENCY_WEIGHT: f64 = 0.5;
+
+/// How often and how recently something was chosen in a palette
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    /// one for every use, halved every [`HALF_LIFE_SECS`] since `last_used`
+    pub score: f64,
+    /// seconds since the unix epoch
+    pub last_used: u64,
+}
+
+impl Usage {
+    /// The score decayed to `now`
+    pub fn score_at(&self, now: u64) -> f64 {
+        let elapsed = now.saturating_sub(self.last_used) as f64;
+        self.score * 0.5f64.powf(elapsed / HALF_LIFE_SECS)
+    }
+}
+
+/// Usage key of an action, which is its key and arguments
+pub fn action_usage_key(action: &PartialAction) -> String {
+    std::iter::once(action.key.as_str())
+        .chain(action.args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Usage key of a note, which is kept across actions that open it
+pub fn path_usage_key(path: &str) -> String {
+    format!("path:{path}")
+}
+
+fn palette_usage_keys(action: &PaletteAction) -> Vec<String> {
+    std::iter::once(action_usage_key(&action.action))
+        .chain(action.path.as_deref().map(path_usage_key))
+        .collect()
+}
+
+/// Ranks items by their match score, boosted by how often and how recently they were chosen.
+/// Usage is decayed to the time of each search, so a palette left open keeps ranking right.
+pub struct FrecencyRanking<T> {
+    usage: HashMap<String, Usage>,
+    usage_keys: fn(&T) -> Vec<String>,
+}
+
+impl<T> FrecencyRanking<T> {
+    fn usages<'a>(&'a self, item: &T) -> impl Iterator<Item = &'a Usage> {
+        (self.usage_keys)(item)
+            .into_iter()
+            .filter_map(|key| self.usage.get(&key))
+    }
+}
+
+impl FrecencyRanking<PaletteAction> {
+    pub fn for_palette(usage: HashMap<String, Usage>) -> Self {
+        Self {
+            usage,
+            usage_keys: palette_usage_keys,
+        }
+    }
+}
+
+impl<T> RankingPolicy<T> for FrecencyRanking<T> {
+    fn score(&self, item: &T, match_score: u32) -> f64 {
+        let now = now_secs();
+        let frecency = self
+            .usages(item)
+            .map(|usage| usage.score_at(now))
+            .fold(0.0, f64::max);
+        match_score as f64 * (1.0 + FRECENCY_WEIGHT * frecency.ln_1p())
+    }
+
+    fn recency(&self, item: &T) -> Option<u64> {
+        self.usages(item).map(|usage| usage.last_used).max()
+    }
+}
+
+fn record_use(usage: &mut HashMap<String, Usage>, key: String, now: u64) {
+    let entry = usage.entry(key).or_default();
+    *entry = Usage {
+        score: entry.score_at(now) + 1.0,
+        last_used: now,
+    };
+}
+
+/// Records that an action was chosen in a palette, along with the note it is about
+pub async fn record_choice(
+    state: &AppState,
+    action: &PartialAction,
+    path: Option<&str>,
+) -> Result<()> {
+    let now = now_secs();
+    write_meta(state, |holder| {
+        holder.update_meta(|meta| {
+            meta.usage
+                .retain(|_, usage| usage.score_at(now) >= MIN_SCORE);
+            record_use(&mut meta.usage, action_usage_key(action), now);
+            if let Some(path) = path {
+                record_use(&mut meta.usage, path_usage_key(path), now);
+            }
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{
+        meta::read_meta,
+        searcher::{SearchField, SearcherManager},
+    };
+
+    const NOW: u64 = 1_000_000_000;
+    const HALF_LIFE: u64 = HALF_LIFE_SECS as u64;
+
+    #[test]
+    fn usage_halves_every_half_life() {
+        let usage = Usage {
+            score: 4.0,
+            last_used: NOW,
+        };
+        assert_eq!(usage.score_at(NOW), 4.0);
+        assert_eq!(usage.score_at(NOW + HALF_LIFE), 2.0);
+        assert_eq!(usage.score_at(NOW + 2 * HALF_LIFE), 1.0);
+        // a clock that went backwards doesn't boost the score
+        assert_eq!(usage.score_at(NOW - HALF_LIFE), 4.0);
+    }
+
+    #[test]
+    fn uses_add_to_the_decayed_score() {
+        let mut usage = HashMap::new();
+        record_use(&mut usage, "a".to_owned(), NOW);
+        record_use(&mut usage, "a".to_owned(), NOW);
+        assert_eq!(usage["a"].score, 2.0);
+        record_use(&mut usage, "a".to_owned(), NOW + HALF_LIFE);
+        assert_eq!(usage["a"].score, 2.0);
+        assert_eq!(usage["a"].last_used, NOW + HALF_LIFE);
+    }
+
+    #[tokio::test]
+    async fn choices_are_recorded_for_the_action_and_the_note() {
+        let state = AppState::in_memory(&[("a.md", "")]);
+        let action = PartialAction {
+            key: "open".to_owned(),
+            args: vec!["a.md".to_owned()],
+        };
+        record_choice(&state, &action, Some("a.md")).await.unwrap();
+        record_choice(&state, &action, Some("a.md")).await.unwrap();
+
+        let usage = read_meta(&state, |holder| holder.meta().usage.clone())
+            .await
+            .unwrap();
+        let mut keys: Vec<&str> = usage.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["open a.md", "path:a.md"]);
+        // the second choice comes right after the first, so hardly anything has decayed
+        assert!(usage["open a.md"].score > 1.99);
+    }
+
+    async fn ranked(ranking: Option<FrecencyRanking<String>>) -> Vec<String> {
+        let mut manager = SearcherManager::new(vec![SearchField {
+            name: "title",
+            scope: "n",
+            weight: 1.0,
+            unscoped: true,
+            get: String::clone,
+        }]);
+        let items = ["apple note".to_owned(), "berry note".to_owned()];
+        let ranking = ranking.map(|ranking| Box::new(ranking) as Box<dyn RankingPolicy<String>>);
+        let id = manager.new_searcher(&items, ranking, None);
+        loop {
+            let results = manager.search(id, "note", 0..10).await.unwrap().unwrap();
+            if !results.running {
+                return results
+                    .matches
+                    .into_iter()
+                    .map(|matched| matched.payload)
+                    .collect();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn a_recent_choice_outranks_an_equal_match() {
+        assert_eq!(ranked(None).await, ["apple note", "berry note"]);
+
+        let mut usage = HashMap::new();
+        record_use(&mut usage, "berry note".to_owned(), now_secs());
+        let ranking = FrecencyRanking {
+            usage,
+            usage_keys: |item: &String| vec![item.clone()],
+        };
+        assert_eq!(ranked(Some(ranking)).await, ["berry note", "apple note"]);
+    }
+}
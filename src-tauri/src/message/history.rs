@@ -0,0 +1,355 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::LazyLock,
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow, bail};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    message::{
+        meta::read_note_meta,
+        note::{Note, WriteNoteResult, write_note},
+    },
+    state::AppState,
+};
+
+/// How long the vault has to stay untouched before pending changes are committed
+const QUIET_PERIOD: Duration = Duration::from_secs(10);
+
+/// Git directory of the history repository, relative to the vault. It is kept apart from any
+/// `.git` folder so brot never commits to a repository the user manages themselves.
+const HISTORY_DIR: &str = ".brot/history";
+
+/// Only notes are tracked, and never anything in dot-directories
+const HISTORY_EXCLUDE: &str = "*\n!*/\n!*.md\n.*/\n";
+
+#[derive(Default)]
+pub struct History {
+    /// descriptions of the changes made since the last commit
+    pending: Vec<String>,
+    /// bumped on every change, so only the last scheduled commit runs
+    generation: u64,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteRevision {
+    pub id: String,
+    /// path the note had at this revision
+    pub path: String,
+    /// seconds since the unix epoch
+    #[ts(type = "number")]
+    pub timestamp: u64,
+    pub message: String,
+}
+
+/// Whether `git` can be run at all. It is missing on Android and not every desktop has it, so
+/// it is checked once instead of failing on every change.
+fn git_available() -> bool {
+    static AVAILABLE: LazyLock<bool> = LazyLock::new(|| {
+        Command::new("git")
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success())
+    });
+    *AVAILABLE
+}
+
+fn git_command(root: &Path) -> Command {
+    let mut command = Command::new("git");
+    command
+        .arg("--git-dir")
+        .arg(root.join(HISTORY_DIR))
+        .arg("--work-tree")
+        .arg(root)
+        .args(["-c", "user.name=brot", "-c", "user.email=brot@localhost"])
+        .args(["-c", "core.quotepath=off"]);
+    command
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = git_command(root).args(args).output()?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn has_commits(root: &Path) -> Result<bool> {
+    Ok(git_command(root)
+        .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+        .output()?
+        .status
+        .success())
+}
+
+fn ensure_repo(root: &Path) -> Result<()> {
+    let git_dir = root.join(HISTORY_DIR);
+    if git_dir.join("HEAD").exists() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(&git_dir)?;
+    git(root, &["init", "--quiet"])?;
+    std::fs::create_dir_all(git_dir.join("info"))?;
+    std::fs::write(git_dir.join("info").join("exclude"), HISTORY_EXCLUDE)?;
+    Ok(())
+}
+
+fn commit(root: &Path, message: &str) -> Result<()> {
+    ensure_repo(root)?;
+    git(root, &["add", "--all"])?;
+    if git(root, &["status", "--porcelain"])?.trim().is_empty() {
+        return Ok(());
+    }
+    git(root, &["commit", "--quiet", "--message", message])?;
+    Ok(())
+}
+
+fn commit_message(pending: &[String]) -> String {
+    let changes: Vec<&String> = pending.iter().unique().collect();
+    match changes.as_slice() {
+        [change] => change.to_string(),
+        changes => format!("{} changes\n\n{}", changes.len(), changes.iter().join("\n")),
+    }
+}
+
+async fn history_root(state: &AppState) -> Option<PathBuf> {
+    state
+        .store()
+        .await
+        .and_then(|store| store.root().map(Path::to_path_buf))
+}
+
+/// Records a change to the vault, which gets committed once the vault has been quiet for a
+/// while
+pub async fn record_change(state: &AppState, description: String) {
+    // the change is committed to its own vault, even if another one is active by then
+    let state = state.pinned().await;
+    let vault = state.vault().await;
+    let generation = {
+        let mut history = vault.history.lock().await;
+        history.pending.push(description);
+        history.generation += 1;
+        history.generation
+    };
+    tokio::spawn(async move {
+        tokio::time::sleep(QUIET_PERIOD).await;
+        if vault.history.lock().await.generation != generation {
+            return;
+        }
+        if let Err(err) = commit_pending(&state).await {
+            println!("failed to record history: {err}");
+        }
+    });
+}
+
+/// Commits all pending changes right away
+pub async fn commit_pending(state: &AppState) -> Result<()> {
+    let vault = state.vault().await;
+    let mut history = vault.history.lock().await;
+    if history.pending.is_empty() {
+        return Ok(());
+    }
+    let message = commit_message(&history.pending);
+    history.pending.clear();
+    let Some(root) = history_root(state).await else {
+        return Ok(());
+    };
+    tokio::task::spawn_blocking(move || {
+        // without git there is no history to keep, which listing revisions reports
+        if !git_available() {
+            return Ok(());
+        }
+        commit(&root, &message)
+    })
+    .await?
+}
+
+/// Lists the revisions of a note, newest first, following it across renames
+pub async fn list_revisions(state: &AppState, path: &str) -> Result<Vec<NoteRevision>> {
+    commit_pending(state).await?;
+    let Some(root) = history_root(state).await else {
+        return Ok(Vec::new());
+    };
+    let path = path.to_owned();
+    tokio::task::spawn_blocking(move || {
+        if !git_available() {
+            bail!("note history needs git, which isn't installed");
+        }
+        if !root.join(HISTORY_DIR).exists() || !has_commits(&root)? {
+            return Ok(Vec::new());
+        }
+        let log = git(
+            &root,
+            &[
+                "log",
+                "--follow",
+                "--name-only",
+                "--format=%x1e%H%x1f%ct%x1f%s",
+                "--",
+                &path,
+            ],
+        )?;
+        log.split('\u{1e}')
+            .filter(|record| !record.trim().is_empty())
+            .map(|record| {
+                let mut lines = record.lines();
+                let header = lines.next().unwrap_or_default();
+                let Some((id, timestamp, message)) = header.split('\u{1f}').collect_tuple() else {
+                    bail!("invalid git log record {header:?}");
+                };
+                Ok(NoteRevision {
+                    id: id.to_owned(),
+                    path: lines
+                        .filter(|line| !line.is_empty())
+                        .last()
+                        .unwrap_or(&path)
+                        .to_owned(),
+                    timestamp: timestamp.parse()?,
+                    message: message.to_owned(),
+                })
+            })
+            .collect()
+    })
+    .await?
+}
+
+async fn find_revision(state: &AppState, path: &str, revision: &str) -> Result<NoteRevision> {
+    list_revisions(state, path)
+        .await?
+        .into_iter()
+        .find(|r| r.id == revision)
+        .ok_or_else(|| anyhow!("no revision {revision:?} of {path:?}"))
+}
+
+/// Unified diff of a note between two revisions, or between a revision and the note as it is
+/// now if `to` is None
+pub async fn diff_revisions(
+    state: &AppState,
+    path: &str,
+    from: &str,
+    to: Option<&str>,
+) -> Result<String> {
+    let from = find_revision(state, path, from).await?;
+    let to = match to {
+        Some(to) => Some(find_revision(state, path, to).await?),
+        None => None,
+    };
+    let Some(root) = history_root(state).await else {
+        bail!("note history is only kept for notes folders on disk");
+    };
+    let path = path.to_owned();
+    tokio::task::spawn_blocking(move || {
+        let to_path = to.as_ref().map_or(&path, |to| &to.path);
+        let mut args = vec!["diff", "--find-renames", &from.id];
+        if let Some(to) = &to {
+            args.push(&to.id);
+        }
+        args.extend(["--", &from.path, to_path]);
+        git(&root, &args)
+    })
+    .await?
+}
+
+/// Overwrites a note with its contents at an older revision. `current_revision` is the
+/// revision of the note the client last saw, so restoring over changes it hasn't seen, like
+/// unsaved edits of another window, conflicts instead.
+pub async fn restore_revision(
+    state: &AppState,
+    path: &str,
+    revision: &str,
+    current_revision: String,
+) -> Result<WriteNoteResult> {
+    let revision = find_revision(state, path, revision).await?;
+    let Some(root) = history_root(state).await else {
+        bail!("note history is only kept for notes folders on disk");
+    };
+    let content = tokio::task::spawn_blocking(move || {
+        git(
+            &root,
+            &["show", &format!("{}:{}", revision.id, revision.path)],
+        )
+    })
+    .await??;
+    let meta = read_note_meta(state, path).await?.unwrap_or_default();
+    write_note(
+        state,
+        path,
+        Note {
+            content,
+            meta,
+            revision: Some(current_revision),
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::note::{content_revision, read_note};
+
+    async fn write(state: &AppState, path: &str, content: &str) {
+        let note = read_note(state, path)
+            .await
+            .unwrap()
+            .unwrap_or_else(Note::new);
+        let note = Note {
+            content: content.to_owned(),
+            ..note
+        };
+        let result = write_note(state, path, note).await.unwrap();
+        assert!(matches!(result, WriteNoteResult::Ok { .. }));
+    }
+
+    #[tokio::test]
+    async fn revisions_can_be_listed_diffed_and_restored() {
+        if !git_available() {
+            return;
+        }
+        let state = AppState::on_disk();
+        write(&state, "a.md", "first\n").await;
+        commit_pending(&state).await.unwrap();
+        write(&state, "a.md", "second\n").await;
+        commit_pending(&state).await.unwrap();
+
+        let revisions = list_revisions(&state, "a.md").await.unwrap();
+        let messages: Vec<&str> = revisions
+            .iter()
+            .map(|revision| revision.message.as_str())
+            .collect();
+        assert_eq!(messages, ["update a.md", "update a.md"]);
+        let first = &revisions[1];
+        assert_eq!(first.path, "a.md");
+
+        let diff = diff_revisions(&state, "a.md", &first.id, None)
+            .await
+            .unwrap();
+        assert!(diff.contains("-first"));
+        assert!(diff.contains("+second"));
+
+        // restoring over a change the client hasn't seen conflicts
+        let stale = content_revision("first\n");
+        let result = restore_revision(&state, "a.md", &first.id, stale).await;
+        assert!(matches!(result.unwrap(), WriteNoteResult::Conflict { .. }));
+
+        let current = content_revision("second\n");
+        let result = restore_revision(&state, "a.md", &first.id, current).await;
+        assert!(matches!(result.unwrap(), WriteNoteResult::Ok { .. }));
+        let note = read_note(&state, "a.md").await.unwrap().unwrap();
+        assert_eq!(note.content, "first\n");
+
+        let root = history_root(&state).await.unwrap();
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -6,9 +6,16 @@ use ts_rs::TS;
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, TS)]
 #[ts(export)]
-#[ts(type = "`note:${string}` | 'pinned' | 'settings' | 'new'")]
+#[ts(type = "`note:${string}` | `vault:${string}:note:${string}` | 'pinned' | 'settings' | 'new'")]
 pub enum Locater {
-    Note { path: String },
+    Note {
+        path: String,
+    },
+    /// a note in a specific vault, rather than in whichever vault is active
+    VaultNote {
+        vault: String,
+        path: String,
+    },
     Pinned,
     Settings,
     New,
@@ -20,15 +27,22 @@ impl Locater {
             "/settings" => Some(Locater::Settings),
             "/new" => Some(Locater::New),
             "/" => Some(Locater::Pinned),
-            "/note" => Some(Locater::Note {
-                path: url.query_pairs().find_map(|(arg, val)| {
-                    if arg == "p" {
-                        Some(val.to_string())
-                    } else {
-                        None
-                    }
-                })?,
-            }),
+            "/note" => {
+                let query = |name: &str| {
+                    url.query_pairs().find_map(|(arg, val)| {
+                        if arg == name {
+                            Some(val.to_string())
+                        } else {
+                            None
+                        }
+                    })
+                };
+                let path = query("p")?;
+                Some(match query("v") {
+                    Some(vault) => Locater::VaultNote { vault, path },
+                    None => Locater::Note { path },
+                })
+            }
             _ => None,
         }
     }
@@ -39,6 +53,7 @@ impl Locater {
             Locater::Pinned => "/".into(),
             Locater::New => "/new".into(),
             Locater::Note { path } => format!("/note?p={path}").into(),
+            Locater::VaultNote { vault, path } => format!("/note?p={path}&v={vault}").into(),
         }
     }
 }
@@ -50,6 +65,7 @@ impl Serialize for Locater {
     {
         let s = match self {
             Locater::Note { path } => format!("note:{}", path),
+            Locater::VaultNote { vault, path } => format!("vault:{}:note:{}", vault, path),
             Locater::Pinned => "pinned".to_string(),
             Locater::Settings => "settings".to_string(),
             Locater::New => "new".to_string(),
@@ -69,6 +85,14 @@ impl<'de> Deserialize<'de> for Locater {
             Ok(Locater::Note {
                 path: rest.to_string(),
             })
+        } else if let Some((vault, path)) = s
+            .strip_prefix("vault:")
+            .and_then(|rest| rest.split_once(":note:"))
+        {
+            Ok(Locater::VaultNote {
+                vault: vault.to_string(),
+                path: path.to_string(),
+            })
         } else {
             match s.as_str() {
                 "pinned" => Ok(Locater::Pinned),
@@ -1,25 +1,68 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Result;
+use schema::{parse_meta, META_VERSION};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use ts_rs::TS;
 
 use crate::{
-    message::tag::{tags_from_meta, TagNode},
-    state::AppState,
+    message::{
+        content_search::SearchIndex,
+        frecency::Usage,
+        tag::{extract_content_tags, extract_tag_units, tags_from_meta, ContentTag, TagNode},
+        tag_alias::{TagNormalization, TagResolver},
+        trash::TrashEntry,
+    },
+    state::{AppState, VaultState},
 };
 
 use super::{
-    folder_manager::{read, read_dir, write},
+    folder_manager::{read, read_dir, read_with_backup, write, write_with_backup},
     note::NoteMeta,
+    property::{parse_front_matter, Properties},
 };
 
-#[derive(Serialize, Deserialize, Default, Clone)]
+pub mod schema;
+
+#[derive(Serialize, Deserialize, Clone)]
 /// maps each note's path to NoteMeta
 pub struct Meta {
+    /// schema version, see [`schema::META_VERSION`]
+    pub version: u64,
     pub notes: HashMap<String, NoteMeta>,
     pub pinned: Vec<String>,
     pub tag_configs: HashMap<String, TagConfig>,
+    #[serde(default)]
+    pub tag_normalization: TagNormalization,
+    /// notes that were deleted and can still be restored
+    #[serde(default)]
+    pub trash: Vec<TrashEntry>,
+    /// how often and how recently actions and notes were chosen in palettes, by usage key
+    #[serde(default)]
+    pub usage: HashMap<String, Usage>,
+    /// fields written by a newer brot, kept so they survive being written back
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for Meta {
+    fn default() -> Self {
+        Self {
+            version: META_VERSION,
+            notes: HashMap::new(),
+            pinned: Vec::new(),
+            tag_configs: HashMap::new(),
+            tag_normalization: TagNormalization::default(),
+            trash: Vec::new(),
+            usage: HashMap::new(),
+            extra: Map::new(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Default, Clone, TS)]
@@ -28,18 +71,56 @@ pub struct Meta {
 pub struct TagConfig {
     pub abbreviation: Option<String>,
     pub hue: Option<f32>,
+    /// name of an icon shown next to the tag
+    pub icon: Option<String>,
+    pub description: Option<String>,
+    /// position among the tag's siblings in the tag tree, lowest first
+    pub sort_order: Option<i32>,
+    /// other tags that mean this one, like "ml" for "machine-learning". They are folded into
+    /// this tag in the tag tree and in queries.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// fields written by a newer brot
+    #[serde(flatten)]
+    #[ts(skip)]
+    pub extra: Map<String, Value>,
 }
 
 pub struct MetaHolder {
     meta: Meta,
     tags: Vec<TagNode>,
+    tag_resolver: TagResolver,
+    /// front matter of each note, which is read from the notes rather than stored in the meta
+    properties: HashMap<String, Properties>,
+    /// tags written in the body or front matter of each note
+    content_tags: HashMap<String, Vec<ContentTag>>,
+    search_index: SearchIndex,
+    /// whether the meta has changes that haven't been written to brot.json yet
+    dirty: bool,
+    /// bumped on every change, so only the last scheduled flush runs
+    generation: u64,
+    /// whether notes were added, removed or retagged since open palettes were last told
+    notes_changed: bool,
 }
 
 impl MetaHolder {
-    fn new(meta: Meta) -> Self {
+    fn new(
+        meta: Meta,
+        properties: HashMap<String, Properties>,
+        content_tags: HashMap<String, Vec<ContentTag>>,
+        search_index: SearchIndex,
+    ) -> Self {
+        let tag_resolver = TagResolver::new(&meta);
         Self {
-            tags: tags_from_meta(&meta),
+            tags: tags_from_meta(&meta, &content_tags, &tag_resolver),
+            tag_resolver,
             meta,
+            properties,
+            content_tags,
+            search_index,
+            dirty: false,
+            generation: 0,
+            notes_changed: true,
         }
     }
 
@@ -51,18 +132,91 @@ impl MetaHolder {
         &self.tags
     }
 
+    /// Whether there are changes that haven't been written to brot.json yet
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn search_index(&self) -> &SearchIndex {
+        &self.search_index
+    }
+
+    pub fn tag_resolver(&self) -> &TagResolver {
+        &self.tag_resolver
+    }
+
+    pub fn properties(&self) -> &HashMap<String, Properties> {
+        &self.properties
+    }
+
+    /// The tags written in the body or front matter of the note at `path`, as written
+    pub fn content_tags(&self, path: &str) -> &[ContentTag] {
+        self.content_tags.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every tag of the note at `path`, from its file name as well as its content, resolved to
+    /// their canonical form
+    pub fn note_tags(&self, path: &str) -> Vec<Vec<String>> {
+        let content_tags = self.content_tags.get(path).into_iter().flatten();
+        extract_tag_units(path)
+            .into_iter()
+            .chain(content_tags.map(|tag| tag.parts.clone()))
+            .map(|parts| self.tag_resolver.resolve(&parts))
+            .collect()
+    }
+
+    /// Updates the properties and tags read from the note at `path` after its content changed
+    pub fn set_content(&mut self, path: &str, content: &str) {
+        let properties = parse_front_matter(content);
+        let content_tags = extract_content_tags(content, properties.as_ref());
+        match properties {
+            Some(properties) => self.properties.insert(path.to_owned(), properties),
+            None => self.properties.remove(path),
+        };
+        if self.content_tags.get(path) != Some(&content_tags) {
+            self.notes_changed = true;
+        }
+        self.content_tags.insert(path.to_owned(), content_tags);
+        self.search_index.insert(path, content);
+        self.tags = tags_from_meta(&self.meta, &self.content_tags, &self.tag_resolver);
+    }
+
     pub fn update_meta<T>(&mut self, mut updater: impl FnMut(&mut Meta) -> T) -> T {
+        let paths: HashSet<String> = self.meta.notes.keys().cloned().collect();
         let res = updater(&mut self.meta);
-        self.tags = tags_from_meta(&self.meta);
+        let notes = &self.meta.notes;
+        self.properties.retain(|path, _| notes.contains_key(path));
+        self.content_tags.retain(|path, _| notes.contains_key(path));
+        self.search_index.retain(|path| notes.contains_key(path));
+        let tag_resolver = TagResolver::new(&self.meta);
+        if tag_resolver != self.tag_resolver
+            || notes.len() != paths.len()
+            || notes.keys().any(|path| !paths.contains(path))
+        {
+            self.notes_changed = true;
+        }
+        self.tag_resolver = tag_resolver;
+        self.tags = tags_from_meta(&self.meta, &self.content_tags, &self.tag_resolver);
         res
     }
 }
 
-const META_PATH: &str = "brot.json";
+pub const META_PATH: &str = "brot.json";
+
+/// How long the meta has to stay unchanged before it is written to brot.json
+const FLUSH_DELAY: Duration = Duration::from_secs(2);
+
+/// Seconds since the unix epoch, which is how timestamps are kept in the meta
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
 
 pub async fn read_meta_file(state: &AppState) -> Result<Meta> {
-    match read(state, META_PATH).await? {
-        Some(contents) => Ok(serde_json::from_str(&contents)?),
+    match read_with_backup(state, META_PATH, |contents| Ok(parse_meta(contents)?)).await? {
+        Some(meta) => Ok(meta),
         None => {
             write(state, META_PATH, serde_json::to_string(&Meta::default())?).await?;
             Ok(Meta::default())
@@ -84,6 +238,34 @@ async fn sync_meta(state: &AppState, meta: &mut Meta) -> Result<()> {
     Ok(())
 }
 
+/// Reads the meta file, syncs it with the notes folder and reads and indexes the contents of
+/// every note
+async fn load_meta(state: &AppState) -> Result<MetaHolder> {
+    let mut meta = read_meta_file(state).await?;
+    sync_meta(state, &mut meta).await?;
+    let mut properties = HashMap::new();
+    let mut content_tags = HashMap::new();
+    let mut search_index = SearchIndex::default();
+    for path in meta.notes.keys() {
+        let Some(content) = read(state, path).await? else {
+            continue;
+        };
+        let note_properties = parse_front_matter(&content);
+        let note_tags = extract_content_tags(&content, note_properties.as_ref());
+        content_tags.insert(path.clone(), note_tags);
+        search_index.insert(path, &content);
+        if let Some(note_properties) = note_properties {
+            properties.insert(path.clone(), note_properties);
+        }
+    }
+    Ok(MetaHolder::new(
+        meta,
+        properties,
+        content_tags,
+        search_index,
+    ))
+}
+
 pub async fn read_note_meta(state: &AppState, path: &str) -> Result<Option<NoteMeta>> {
     Ok(read_meta(state, |holder| {
         holder.meta.notes.get(path).as_deref().cloned()
@@ -95,42 +277,172 @@ pub async fn read_meta<T>(
     state: &AppState,
     mut function: impl FnMut(&MetaHolder) -> T,
 ) -> Result<T> {
-    let mut guard = state.meta.lock().await;
+    let vault = state.vault().await;
+    let mut guard = vault.meta.lock().await;
     if let Some(ref meta) = *guard {
         Ok(function(meta))
     } else {
-        let mut meta = read_meta_file(state).await?;
-        sync_meta(state, &mut meta).await?;
-        let holder = MetaHolder::new(meta);
+        let mut holder = load_meta(state).await?;
+        notify_note_changes(&vault, &mut holder);
         let res = function(&holder);
         *guard = Some(holder);
         Ok(res)
     }
 }
 
+/// Applies a change to the cached meta without writing it to disk. Returns None if the meta
+/// hasn't been loaded yet, since it will be synced with the folder when it is.
+pub async fn update_cached_meta<T>(
+    state: &AppState,
+    function: impl FnOnce(&mut MetaHolder) -> T,
+) -> Option<T> {
+    let vault = state.vault().await;
+    let mut guard = vault.meta.lock().await;
+    let holder = guard.as_mut()?;
+    let res = function(holder);
+    notify_note_changes(&vault, holder);
+    Some(res)
+}
+
+/// Tells open palettes that notes were added, removed or retagged, so they can update
+fn notify_note_changes(vault: &VaultState, holder: &mut MetaHolder) {
+    if std::mem::take(&mut holder.notes_changed) {
+        vault.note_changes.send_modify(|version| *version += 1);
+    }
+}
+
+/// Applies a change to the meta. The change is written to brot.json once the meta has been
+/// unchanged for a while, or when [`flush_meta`] is called.
 pub async fn write_meta<T>(
     state: &AppState,
 
     mut function: impl FnMut(&mut MetaHolder) -> T,
 ) -> Result<T> {
-    let mut guard = state.meta.lock().await;
-    if let Some(ref mut holder) = *guard {
-        let res = function(holder);
-        write(state, META_PATH, serde_json::to_string(&holder.meta())?).await?;
-        Ok(res)
-    } else {
-        let meta = read_meta_file(state).await?;
-        let mut holder = MetaHolder::new(meta);
-        let res = function(&mut holder);
-        write(state, META_PATH, serde_json::to_string(&holder.meta())?).await?;
-        *guard = Some(holder);
-        Ok(res)
+    let vault = state.vault().await;
+    let mut guard = vault.meta.lock().await;
+    if guard.is_none() {
+        *guard = Some(load_meta(state).await?);
     }
+    let holder = guard.as_mut().expect("meta was just loaded");
+    let res = function(holder);
+    notify_note_changes(&vault, holder);
+    holder.dirty = true;
+    holder.generation += 1;
+    let generation = holder.generation;
+    drop(guard);
+    schedule_flush(state, vault, generation);
+    Ok(res)
+}
+
+fn schedule_flush(state: &AppState, vault: Arc<VaultState>, generation: u64) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(FLUSH_DELAY).await;
+        let unchanged = vault
+            .meta
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|holder| holder.generation == generation);
+        // switching vaults flushes the vault being left, so only the active one is written here
+        if !unchanged || !Arc::ptr_eq(&vault, &state.vault().await) {
+            return;
+        }
+        if let Err(err) = flush_meta(&state).await {
+            println!("failed to write meta: {err}");
+        }
+    });
+}
+
+/// Writes the meta of the state's vault to brot.json right away, if it has unwritten changes
+pub async fn flush_meta(state: &AppState) -> Result<()> {
+    let vault = state.vault().await;
+    let mut guard = vault.meta.lock().await;
+    if let Some(holder) = guard.as_mut()
+        && holder.dirty
+    {
+        write_with_backup(state, META_PATH, serde_json::to_string(holder.meta())?).await?;
+        holder.dirty = false;
+    }
+    Ok(())
+}
+
+/// Writes the meta of every cached vault that has unwritten changes, not just the active one
+pub async fn flush_all_meta(state: &AppState) -> Result<()> {
+    let names: Vec<String> = state.vaults.lock().await.keys().cloned().collect();
+    for name in names {
+        // the vault may have been removed from the settings already
+        if let Ok(state) = state.for_vault(name).await {
+            flush_meta(&state).await?;
+        }
+    }
+    Ok(())
 }
 
 pub async fn write_note_meta(state: &AppState, path: &str, note_meta: NoteMeta) -> Result<()> {
     write_meta(state, move |holder| {
-        holder.meta.notes.insert(path.to_owned(), note_meta.clone());
+        if holder
+            .meta
+            .notes
+            .insert(path.to_owned(), note_meta.clone())
+            .is_none()
+        {
+            holder.notes_changed = true;
+        }
     })
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn loading_syncs_the_meta_with_the_notes() {
+        let mut meta = Meta::default();
+        let opened = NoteMeta {
+            open_count: 3,
+            ..NoteMeta::default()
+        };
+        meta.notes.insert("a.md".to_owned(), opened);
+        meta.notes.insert("gone.md".to_owned(), NoteMeta::default());
+        let state = AppState::in_memory_with_meta(
+            &[
+                ("a.md", "hello #draft"),
+                ("folder/b.md", ""),
+                ("image.png", ""),
+            ],
+            &meta,
+        );
+        let (mut paths, open_count, tags) = read_meta(&state, |holder| {
+            let paths: Vec<String> = holder.meta().notes.keys().cloned().collect();
+            let open_count = holder.meta().notes["a.md"].open_count;
+            (paths, open_count, holder.note_tags("a.md"))
+        })
+        .await
+        .unwrap();
+        paths.sort();
+        assert_eq!(paths, ["a.md", "folder/b.md"]);
+        assert_eq!(open_count, 3);
+        assert_eq!(tags, [["draft"]]);
+    }
+
+    #[tokio::test]
+    async fn flushing_keeps_unknown_fields() {
+        let mut meta = Meta::default();
+        meta.extra.insert("future".to_owned(), Value::Bool(true));
+        let state = AppState::in_memory_with_meta(&[], &meta);
+        write_meta(&state, |holder| {
+            holder.update_meta(|meta| meta.pinned.push("a.md".to_owned()))
+        })
+        .await
+        .unwrap();
+        flush_meta(&state).await.unwrap();
+
+        let written = read(&state, META_PATH).await.unwrap().unwrap();
+        let written: Value = serde_json::from_str(&written).unwrap();
+        assert_eq!(written["future"], Value::Bool(true));
+        assert_eq!(written["pinned"], serde_json::json!(["a.md"]));
+        assert_eq!(written["version"], serde_json::json!(META_VERSION));
+    }
+}
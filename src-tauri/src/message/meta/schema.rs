@@ -0,0 +1,102 @@
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::message::meta::Meta;
+
+/// Version of the brot.json schema this brot writes
+pub const META_VERSION: u64 = 1;
+
+/// Migrates the json of a brot.json from one version to the next. `MIGRATIONS[n]` upgrades
+/// version `n` to version `n + 1`, so the last one always produces [`META_VERSION`].
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+const MIGRATIONS: [Migration; META_VERSION as usize] = [fill_missing_fields];
+
+#[derive(Debug)]
+pub enum MetaError {
+    /// the file isn't json at all
+    Syntax(serde_json::Error),
+    /// the top level of the file isn't a json object
+    NotAnObject,
+    /// the version field isn't a number
+    InvalidVersion(Value),
+    /// upgrading from version `from` failed
+    Migration { from: u64, message: String },
+    /// the file is json, but doesn't match the schema
+    Schema {
+        version: u64,
+        error: serde_json::Error,
+    },
+}
+
+impl fmt::Display for MetaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetaError::Syntax(err) => write!(f, "brot.json is not valid json: {err}"),
+            MetaError::NotAnObject => write!(f, "brot.json does not contain a json object"),
+            MetaError::InvalidVersion(version) => {
+                write!(f, "brot.json has an invalid version {version}")
+            }
+            MetaError::Migration { from, message } => write!(
+                f,
+                "failed to upgrade brot.json from version {from} to {}: {message}",
+                from + 1
+            ),
+            MetaError::Schema { version, error } => {
+                write!(f, "brot.json (version {version}) is invalid: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MetaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MetaError::Syntax(err) | MetaError::Schema { error: err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a brot.json of any version, upgrading it to the current schema. Files written by a
+/// newer brot are read as well as possible, keeping the fields this brot doesn't know about.
+pub fn parse_meta(contents: &str) -> Result<Meta, MetaError> {
+    let value: Value = serde_json::from_str(contents).map_err(MetaError::Syntax)?;
+    let Value::Object(mut object) = value else {
+        return Err(MetaError::NotAnObject);
+    };
+    let version = match object.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| MetaError::InvalidVersion(version.clone()))?,
+    };
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(&mut object).map_err(|message| MetaError::Migration {
+            from: from as u64,
+            message,
+        })?;
+    }
+    object.insert("version".to_owned(), version.max(META_VERSION).into());
+    serde_json::from_value(Value::Object(object))
+        .map_err(|error| MetaError::Schema { version, error })
+}
+
+/// Files from before brot.json was versioned may lack fields that were added over time
+fn fill_missing_fields(object: &mut Map<String, Value>) -> Result<(), String> {
+    for (field, default) in [
+        ("notes", Value::Object(Map::new())),
+        ("pinned", Value::Array(Vec::new())),
+        ("tag_configs", Value::Object(Map::new())),
+        ("trash", Value::Array(Vec::new())),
+    ] {
+        match object.get(field) {
+            None | Some(Value::Null) => {
+                object.insert(field.to_owned(), default);
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
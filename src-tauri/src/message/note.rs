@@ -1,13 +1,22 @@
+use std::collections::HashMap;
+
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 use ts_rs::TS;
 
 use crate::{
     message::{
-        folder_manager::{read, remove_file, write},
-        meta::{read_meta, read_note_meta, write_meta, write_note_meta},
+        folder_manager::{content_hash, read, rename_file, write},
+        frecency::path_usage_key,
+        history::record_change,
+        meta::{
+            flush_meta, now_secs, read_meta, read_note_meta, update_cached_meta, write_meta,
+            write_note_meta,
+        },
         title::title_to_path,
+        trash::trash_note,
     },
     state::AppState,
 };
@@ -20,17 +29,39 @@ use super::folder_manager::file_exists;
 pub struct Note {
     pub content: String,
     pub meta: NoteMeta,
+    /// revision of the content on disk this note is based on. Writing a note whose revision
+    /// no longer matches the disk is rejected, writing one without a revision always succeeds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub revision: Option<String>,
 }
 
 impl Note {
     pub fn new() -> Self {
         Note {
-            meta: NoteMeta { selection: None },
+            meta: NoteMeta::default(),
             content: "".to_owned(),
+            revision: None,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, TS, Clone)]
+#[ts(export)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WriteNoteResult {
+    #[serde(rename_all = "camelCase")]
+    Ok { revision: String },
+    /// the note changed on disk since the revision the write was based on, so nothing was
+    /// written
+    #[serde(rename_all = "camelCase")]
+    Conflict { current: Note },
+}
+
+pub fn content_revision(content: &str) -> String {
+    format!("{:016x}", content_hash(content))
+}
+
 #[derive(Serialize, Deserialize, TS, Clone, Debug)]
 #[ts(export)]
 #[serde(rename_all = "camelCase")]
@@ -38,31 +69,149 @@ pub struct NoteMeta {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub selection: Option<(u32, u32)>,
+    /// seconds since the unix epoch, None for notes that existed before brot kept track
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub created: Option<u64>,
+    /// when the content last changed, in seconds since the unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub modified: Option<u64>,
+    /// when the note was last opened, in seconds since the unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "number")]
+    pub opened: Option<u64>,
+    #[serde(default)]
+    pub open_count: u32,
+    #[serde(default)]
+    pub word_count: u32,
+    #[serde(default)]
+    pub char_count: u32,
+    /// fields written by a newer brot, kept so they survive being written back
+    #[serde(flatten)]
+    #[ts(skip)]
+    pub extra: Map<String, Value>,
 }
 
 impl Default for NoteMeta {
     fn default() -> Self {
-        Self { selection: None }
+        Self {
+            selection: None,
+            created: None,
+            modified: None,
+            opened: None,
+            open_count: 0,
+            word_count: 0,
+            char_count: 0,
+            extra: Map::new(),
+        }
     }
 }
 
-pub async fn read_note(state: &AppState, path: &str) -> Result<Option<Note>> {
-    println!("reading note {:?}", path);
+impl NoteMeta {
+    /// Updates the stats that depend on the note's content after it changed
+    pub fn record_content(&mut self, content: &str, now: u64) {
+        self.modified = Some(now);
+        self.word_count = content.split_whitespace().count() as u32;
+        self.char_count = content.chars().count() as u32;
+    }
 
+    /// Takes what the frontend may change from `edited`, keeping everything brot tracks itself.
+    /// The frontend never sees `extra`, so the stored fields are kept too.
+    fn merge_edits(&mut self, edited: NoteMeta) {
+        self.selection = edited.selection;
+    }
+}
+
+/// Reads a note without counting it as opened
+async fn load_note(state: &AppState, path: &str) -> Result<Option<Note>> {
     let meta = read_note_meta(state, path).await?;
     let content = read(state, path).await?;
-    Ok(meta
-        .zip(content)
-        .map(|(meta, content)| Note { meta, content }))
+    Ok(meta.zip(content).map(|(meta, content)| Note {
+        revision: Some(content_revision(&content)),
+        meta,
+        content,
+    }))
+}
+
+/// Reads a note for showing it, which counts as opening it
+pub async fn read_note(state: &AppState, path: &str) -> Result<Option<Note>> {
+    println!("reading note {:?}", path);
+
+    let Some(mut note) = load_note(state, path).await? else {
+        return Ok(None);
+    };
+    let now = now_secs();
+    note.meta.opened = Some(now);
+    note.meta.open_count += 1;
+    write_meta(state, |holder| {
+        holder.update_meta(|meta| {
+            if let Some(note_meta) = meta.notes.get_mut(path) {
+                note_meta.opened = Some(now);
+                note_meta.open_count += 1;
+            }
+        })
+    })
+    .await?;
+    Ok(Some(note))
 }
 
-pub async fn write_note(state: &AppState, path: &str, note: Note) -> Result<()> {
+/// Metas of the notes at `paths`, or of every note if it is None. Paths without a note are
+/// left out.
+pub async fn read_note_metas(
+    state: &AppState,
+    paths: Option<Vec<String>>,
+) -> Result<HashMap<String, NoteMeta>> {
+    read_meta(state, |holder| match &paths {
+        Some(paths) => paths
+            .iter()
+            .filter_map(|path| {
+                let meta = holder.meta().notes.get(path)?;
+                Some((path.clone(), meta.clone()))
+            })
+            .collect(),
+        None => holder.meta().notes.clone(),
+    })
+    .await
+}
+
+pub async fn write_note(state: &AppState, path: &str, note: Note) -> Result<WriteNoteResult> {
     println!("updating note {:?}", path);
 
-    write_note_meta(state, path, note.meta).await?;
+    let _guard = state.note_write_lock.lock().await;
+    let current = read(state, path).await?;
+    if let Some(revision) = &note.revision
+        && let Some(current) = current.clone()
+        && content_revision(&current) != *revision
+        && current != note.content
+    {
+        println!("rejecting stale write to {:?}", path);
+        let meta = read_note_meta(state, path).await?.unwrap_or_default();
+        return Ok(WriteNoteResult::Conflict {
+            current: Note {
+                revision: Some(content_revision(&current)),
+                content: current,
+                meta,
+            },
+        });
+    }
+
+    let revision = content_revision(&note.content);
+    let now = now_secs();
+    let mut meta = read_note_meta(state, path).await?.unwrap_or_default();
+    meta.merge_edits(note.meta);
+    if current.is_none() {
+        meta.created = Some(now);
+    }
+    if current.as_ref() != Some(&note.content) {
+        meta.record_content(&note.content, now);
+    }
+    write_note_meta(state, path, meta).await?;
+    update_cached_meta(state, |holder| holder.set_content(path, &note.content)).await;
     write(state, path, note.content).await?;
+    record_change(state, format!("update {path}")).await;
 
-    Ok(())
+    Ok(WriteNoteResult::Ok { revision })
 }
 
 pub async fn create_note(state: &AppState, title: String) -> Result<Option<String>> {
@@ -75,14 +224,9 @@ pub async fn create_note(state: &AppState, title: String) -> Result<Option<Strin
     Ok(path)
 }
 
+/// Moves a note to the trash
 pub async fn delete_note(state: &AppState, path: &str) -> Result<()> {
-    remove_file(state, &path).await?;
-    write_meta(state, |holder| {
-        holder.update_meta(|meta| meta.notes.remove_entry(path));
-        holder.update_meta(|meta| meta.pinned.retain(|p| p != path));
-    })
-    .await?;
-    Ok(())
+    trash_note(state, path).await
 }
 
 /// Creates note path from title if that note path doesn't already exist, else returns None
@@ -103,22 +247,112 @@ pub async fn update_path(
 ) -> Result<Option<String>> {
     let new_path = create_note_path(state, &new_title).await?;
     if let Some(new_path) = new_path.clone() {
-        let Some(note) = read_note(state, &current_path).await? else {
+        let Some(content) = read(state, &current_path).await? else {
             bail!("note does not exist")
         };
-        let pinned_index = read_meta(state, |holder| {
-            holder.meta().pinned.iter().position(|p| *p == current_path)
+        // the note keeps its timestamps and stats, so its meta is moved rather than recreated
+        let note_meta = read_note_meta(state, &current_path)
+            .await?
+            .unwrap_or_default();
+        // brot.json on disk should match the notes on disk before and after they are moved
+        flush_meta(state).await?;
+        rename_file(state, &current_path, &new_path).await?;
+        record_change(state, format!("rename {current_path} to {new_path}")).await;
+
+        write_meta(state, |holder| {
+            holder.update_meta(|meta| {
+                meta.notes.remove(&current_path);
+                meta.notes.insert(new_path.clone(), note_meta.clone());
+                for pinned in meta.pinned.iter_mut().filter(|p| **p == current_path) {
+                    *pinned = new_path.clone();
+                }
+                // the note keeps how often it was opened
+                if let Some(usage) = meta.usage.remove(&path_usage_key(&current_path)) {
+                    meta.usage.insert(path_usage_key(&new_path), usage);
+                }
+            });
+            holder.set_content(&new_path, &content);
         })
         .await?;
-        write_note(state, &new_path, note).await?;
-        delete_note(state, &current_path).await?;
+        flush_meta(state).await?;
+    }
+    Ok(new_path)
+}
 
-        if let Some(index) = pinned_index {
-            write_meta(state, |holder| {
-                holder.update_meta(|meta| meta.pinned.insert(index, new_path.clone()));
-            })
-            .await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::meta::Meta;
+
+    #[tokio::test]
+    async fn written_notes_can_be_read_back() {
+        let state = AppState::in_memory(&[]);
+        let path = create_note(&state, "hello world".to_owned())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, "hello_world.md");
+        let note = Note {
+            content: "one two three".to_owned(),
+            ..Note::new()
+        };
+        write_note(&state, &path, note).await.unwrap();
+
+        let note = read_note(&state, &path).await.unwrap().unwrap();
+        assert_eq!(note.content, "one two three");
+        assert_eq!(note.meta.word_count, 3);
+        assert_eq!(note.meta.open_count, 1);
+        assert!(note.meta.created.is_some());
+        // a note that already exists isn't created again
+        let again = create_note(&state, "hello world".to_owned()).await.unwrap();
+        assert_eq!(again, None);
+    }
+
+    #[test]
+    fn revisions_are_stable() {
+        // the first 8 bytes of the sha-256 of the content, which never change between builds
+        assert_eq!(content_revision(""), "e3b0c44298fc1c14");
+        assert_eq!(content_revision("abc"), "ba7816bf8f01cfea");
+    }
+
+    #[tokio::test]
+    async fn stale_writes_conflict() {
+        let state = AppState::in_memory(&[("a.md", "first")]);
+        let stale = read_note(&state, "a.md").await.unwrap().unwrap();
+        let newer = Note {
+            content: "second".to_owned(),
+            ..stale.clone()
+        };
+        let result = write_note(&state, "a.md", newer).await.unwrap();
+        assert!(matches!(result, WriteNoteResult::Ok { .. }));
+
+        let stale = Note {
+            content: "third".to_owned(),
+            ..stale
+        };
+        match write_note(&state, "a.md", stale).await.unwrap() {
+            WriteNoteResult::Conflict { current } => assert_eq!(current.content, "second"),
+            WriteNoteResult::Ok { .. } => panic!("a stale write was accepted"),
         }
     }
-    Ok(new_path)
+
+    #[tokio::test]
+    async fn saving_keeps_unknown_meta_fields() {
+        let mut colored = NoteMeta::default();
+        colored.extra.insert("color".to_owned(), Value::from("red"));
+        let mut meta = Meta::default();
+        meta.notes.insert("a.md".to_owned(), colored);
+        let state = AppState::in_memory_with_meta(&[("a.md", "text")], &meta);
+        let note = read_note(&state, "a.md").await.unwrap().unwrap();
+        // the frontend doesn't know about unknown fields, so it never sends them back
+        let note = Note {
+            content: "new text".to_owned(),
+            meta: NoteMeta::default(),
+            ..note
+        };
+        write_note(&state, "a.md", note).await.unwrap();
+
+        let meta = read_note_meta(&state, "a.md").await.unwrap().unwrap();
+        assert_eq!(meta.extra.get("color"), Some(&Value::from("red")));
+    }
 }
@@ -0,0 +1,316 @@
+use std::iter::Peekable;
+
+use anyhow::{Result, bail};
+
+use crate::{
+    message::{
+        meta::read_meta,
+        tag::{split_content_tag, split_tag_unit},
+        tag_alias::TagResolver,
+        title::path_to_title,
+    },
+    state::AppState,
+};
+
+/// A filter over notes, parsed from a query like `-work and (-urgent or -review) and not
+/// -archive`.
+///
+/// - `-tag--sub` and `#tag/sub` match notes with the tag or one of its subtags, in the file
+///   name or the content
+/// - any other word, or a `"quoted phrase"`, matches notes whose title contains it, ignoring
+///   case
+/// - `and`, `or`, `not` and parentheses combine them, and words next to each other are and-ed
+#[derive(Clone, Debug, PartialEq)]
+pub enum NoteQuery {
+    Tag(Vec<String>),
+    Text(String),
+    Not(Box<NoteQuery>),
+    And(Vec<NoteQuery>),
+    Or(Vec<NoteQuery>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Tag(Vec<String>),
+    Text(String),
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Open => "\"(\"".to_owned(),
+        Token::Close => "\")\"".to_owned(),
+        Token::And => "\"and\"".to_owned(),
+        Token::Or => "\"or\"".to_owned(),
+        Token::Not => "\"not\"".to_owned(),
+        Token::Tag(parts) => format!("tag {:?}", parts.join("--")),
+        Token::Text(text) => format!("{text:?}"),
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, c)) => text.push(c),
+                        None => bail!("unclosed quote in query"),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            _ => {
+                let mut end = query.len();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        end = i;
+                        break;
+                    }
+                    chars.next();
+                }
+                let word = &query[start..end];
+                let token = match word.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ if word.starts_with('-') => Token::Tag(split_tag_unit(word)),
+                    _ if word.starts_with('#') => Token::Tag(split_content_tag(word)),
+                    _ => Token::Text(word.to_owned()),
+                };
+                if let Token::Tag(parts) = &token
+                    && (parts.is_empty() || parts.iter().any(String::is_empty))
+                {
+                    bail!("invalid tag {word:?} in query");
+                }
+                tokens.push(token);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+type Tokens = Peekable<std::vec::IntoIter<Token>>;
+
+/// Most `not`s and parentheses a query can nest, so parsing and matching it can't run out of
+/// stack
+const MAX_DEPTH: usize = 64;
+
+/// `depth` is how many `not`s and parentheses the tokens are nested in
+fn parse_or(tokens: &mut Tokens, depth: usize) -> Result<NoteQuery> {
+    let mut terms = vec![parse_and(tokens, depth)?];
+    while tokens.next_if_eq(&Token::Or).is_some() {
+        terms.push(parse_and(tokens, depth)?);
+    }
+    Ok(if terms.len() == 1 {
+        terms.remove(0)
+    } else {
+        NoteQuery::Or(terms)
+    })
+}
+
+fn parse_and(tokens: &mut Tokens, depth: usize) -> Result<NoteQuery> {
+    let mut terms = vec![parse_not(tokens, depth)?];
+    loop {
+        match tokens.peek() {
+            Some(Token::And) => {
+                tokens.next();
+                terms.push(parse_not(tokens, depth)?);
+            }
+            Some(Token::Not | Token::Open | Token::Tag(_) | Token::Text(_)) => {
+                terms.push(parse_not(tokens, depth)?);
+            }
+            _ => break,
+        }
+    }
+    Ok(if terms.len() == 1 {
+        terms.remove(0)
+    } else {
+        NoteQuery::And(terms)
+    })
+}
+
+fn parse_not(tokens: &mut Tokens, depth: usize) -> Result<NoteQuery> {
+    if matches!(tokens.peek(), Some(Token::Not | Token::Open)) && depth >= MAX_DEPTH {
+        bail!("query is nested too deeply");
+    }
+    if tokens.next_if_eq(&Token::Not).is_some() {
+        return Ok(NoteQuery::Not(Box::new(parse_not(tokens, depth + 1)?)));
+    }
+    match tokens.next() {
+        Some(Token::Open) => {
+            let query = parse_or(tokens, depth + 1)?;
+            if tokens.next() != Some(Token::Close) {
+                bail!("missing \")\" in query");
+            }
+            Ok(query)
+        }
+        Some(Token::Tag(parts)) => Ok(NoteQuery::Tag(parts)),
+        Some(Token::Text(text)) => Ok(NoteQuery::Text(text.to_lowercase())),
+        Some(token) => bail!("unexpected {} in query", describe(&token)),
+        None => bail!("query ended unexpectedly"),
+    }
+}
+
+impl NoteQuery {
+    /// Parses a query. An empty query matches every note.
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut tokens = tokenize(query)?.into_iter().peekable();
+        if tokens.peek().is_none() {
+            return Ok(NoteQuery::And(Vec::new()));
+        }
+        let parsed = parse_or(&mut tokens, 0)?;
+        if let Some(token) = tokens.next() {
+            bail!("unexpected {} in query", describe(&token));
+        }
+        Ok(parsed)
+    }
+
+    /// Resolves the tags of the query to their canonical form, like the tags of notes are
+    pub fn resolve_tags(&self, resolver: &TagResolver) -> Self {
+        match self {
+            NoteQuery::Tag(parts) => NoteQuery::Tag(resolver.resolve(parts)),
+            NoteQuery::Text(text) => NoteQuery::Text(text.clone()),
+            NoteQuery::Not(query) => NoteQuery::Not(Box::new(query.resolve_tags(resolver))),
+            NoteQuery::And(queries) => {
+                NoteQuery::And(queries.iter().map(|q| q.resolve_tags(resolver)).collect())
+            }
+            NoteQuery::Or(queries) => {
+                NoteQuery::Or(queries.iter().map(|q| q.resolve_tags(resolver)).collect())
+            }
+        }
+    }
+
+    /// Whether a note with this lowercased title and these tags matches
+    pub fn matches(&self, title: &str, tags: &[Vec<String>]) -> bool {
+        match self {
+            NoteQuery::Tag(parts) => tags.iter().any(|tag| tag.starts_with(parts)),
+            NoteQuery::Text(text) => title.contains(text.as_str()),
+            NoteQuery::Not(query) => !query.matches(title, tags),
+            NoteQuery::And(queries) => queries.iter().all(|query| query.matches(title, tags)),
+            NoteQuery::Or(queries) => queries.iter().any(|query| query.matches(title, tags)),
+        }
+    }
+}
+
+/// Paths of the notes matching `query`, sorted
+pub async fn query_notes(state: &AppState, query: &NoteQuery) -> Result<Vec<String>> {
+    let mut paths: Vec<String> = read_meta(state, |holder| {
+        let query = query.resolve_tags(holder.tag_resolver());
+        holder
+            .meta()
+            .notes
+            .keys()
+            .filter(|path| {
+                query.matches(&path_to_title(path).to_lowercase(), &holder.note_tags(path))
+            })
+            .cloned()
+            .collect()
+    })
+    .await?;
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(parts: &[&str]) -> NoteQuery {
+        NoteQuery::Tag(parts.iter().map(|part| part.to_string()).collect())
+    }
+
+    fn text(text: &str) -> NoteQuery {
+        NoteQuery::Text(text.to_owned())
+    }
+
+    fn error(query: &str) -> String {
+        NoteQuery::parse(query).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            NoteQuery::parse("a or b and c").unwrap(),
+            NoteQuery::Or(vec![text("a"), NoteQuery::And(vec![text("b"), text("c")])])
+        );
+    }
+
+    #[test]
+    fn words_next_to_each_other_are_anded() {
+        assert_eq!(
+            NoteQuery::parse("Plan -work").unwrap(),
+            NoteQuery::And(vec![text("plan"), tag(&["work"])])
+        );
+    }
+
+    #[test]
+    fn not_applies_to_a_whole_group() {
+        assert_eq!(
+            NoteQuery::parse("not (a or b) c").unwrap(),
+            NoteQuery::And(vec![
+                NoteQuery::Not(Box::new(NoteQuery::Or(vec![text("a"), text("b")]))),
+                text("c"),
+            ])
+        );
+    }
+
+    #[test]
+    fn quoted_phrases_are_one_word() {
+        assert_eq!(
+            NoteQuery::parse(r#""Weekly And Review""#).unwrap(),
+            text("weekly and review")
+        );
+    }
+
+    #[test]
+    fn both_tag_spellings_parse_to_the_same_tag() {
+        assert_eq!(NoteQuery::parse("#tag/sub").unwrap(), tag(&["tag", "sub"]));
+        assert_eq!(NoteQuery::parse("-tag--sub").unwrap(), tag(&["tag", "sub"]));
+    }
+
+    #[test]
+    fn malformed_queries_are_errors() {
+        assert_eq!(error(r#""open"#), "unclosed quote in query");
+        assert_eq!(error("(a or b"), "missing \")\" in query");
+        assert_eq!(error("a and"), "query ended unexpectedly");
+        assert_eq!(error("a )"), "unexpected \")\" in query");
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        assert_eq!(error(&"(".repeat(10_000)), "query is nested too deeply");
+        assert_eq!(error(&"not ".repeat(10_000)), "query is nested too deeply");
+        let nested = format!("{}a{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+        assert_eq!(NoteQuery::parse(&nested).unwrap(), text("a"));
+    }
+
+    #[test]
+    fn subtags_match_their_parent_tag() {
+        let query = NoteQuery::parse("-project and not -project--done").unwrap();
+        let tags = |parts: &[&str]| vec![parts.iter().map(|p| p.to_string()).collect()];
+        assert!(query.matches("plan", &tags(&["project", "alpha"])));
+        assert!(!query.matches("plan", &tags(&["project", "done"])));
+        assert!(!query.matches("plan", &[]));
+    }
+}
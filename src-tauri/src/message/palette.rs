@@ -1,32 +1,139 @@
 use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use ts_rs::TS;
 
-use crate::message::palette_action::{get_palette_actions, Matched, PaletteAction};
-use crate::message::{action::PartialActionFilter, searcher::SearcherId};
-use crate::state::AppState;
+use crate::message::action::PartialActionFilter;
+use crate::message::frecency::FrecencyRanking;
+use crate::message::meta::read_meta;
+use crate::message::palette_action::{get_palette_actions, PaletteAction, SearchResults};
+use crate::message::searcher::{evict_idle_searchers, SearcherId};
+use crate::missed_events::Event;
+use crate::state::{AppState, VaultState};
+
+/// Results of a palette search that nucleo was still matching when it was answered
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct PaletteResults {
+    pub id: SearcherId,
+    pub search: String,
+    pub start: u32,
+    pub end: u32,
+    pub results: SearchResults<PaletteAction>,
+}
+
+/// How long a palette waits after notes change before updating, so a burst of changes like a
+/// tag rename only updates it once
+const UPDATE_DELAY: Duration = Duration::from_millis(200);
 
 pub async fn create_palette(
     state: &AppState,
     palette_key: String,
     filters: Vec<PartialActionFilter>,
+    window: Option<&str>,
 ) -> Result<SearcherId> {
-    let actions = get_palette_actions(state, &palette_key, filters).await?;
-    let mut palettes = state.palettes.write().await;
-    Ok(palettes.new_searcher(&actions))
+    let actions = get_palette_actions(state, &palette_key, filters.clone()).await?;
+    let usage = read_meta(state, |holder| holder.meta().usage.clone()).await?;
+    let ranking = FrecencyRanking::for_palette(usage);
+    let vault = state.vault().await;
+    let mut palettes = vault.palettes.write().await;
+    let id = palettes.new_searcher(&actions, Some(Box::new(ranking)), window);
+    let task = watch_notes(state.clone(), vault.clone(), id, palette_key, filters);
+    palettes.set_task(id, task);
+    Ok(id)
+}
+
+/// Keeps the palette's actions up to date with the notes of its vault. The returned task is
+/// stopped when the palette is dropped.
+fn watch_notes(
+    state: AppState,
+    vault: Arc<VaultState>,
+    id: SearcherId,
+    palette_key: String,
+    filters: Vec<PartialActionFilter>,
+) -> JoinHandle<()> {
+    let mut changes = vault.note_changes.subscribe();
+    tokio::spawn(async move {
+        while changes.changed().await.is_ok() {
+            tokio::time::sleep(UPDATE_DELAY).await;
+            changes.borrow_and_update();
+            if !vault.palettes.read().await.contains(id) {
+                return;
+            }
+            // the actions are generated from the active vault, which could be another one now
+            if !Arc::ptr_eq(&vault, &state.vault().await) {
+                continue;
+            }
+            let actions = match get_palette_actions(&state, &palette_key, filters.clone()).await {
+                Ok(actions) => actions,
+                Err(err) => {
+                    println!("failed to update palette: {err}");
+                    continue;
+                }
+            };
+            match vault
+                .palettes
+                .read()
+                .await
+                .update_searcher(id, actions)
+                .await
+            {
+                Ok(true) => {}
+                Ok(false) => return,
+                Err(err) => println!("failed to update palette: {err}"),
+            }
+        }
+    })
 }
 
+/// Searches a palette. If nucleo is still matching when the results are returned, the window
+/// labeled `window` is sent the complete results once it is done.
 pub async fn search_palette(
     state: &AppState,
     id: SearcherId,
     search: String,
     range: Range<u32>,
-) -> Result<Option<Vec<Matched<PaletteAction>>>> {
-    let palettes = state.palettes.read().await;
-    Ok(palettes.search(id, &search, range).await?)
+    window: Option<&str>,
+) -> Result<Option<SearchResults<PaletteAction>>> {
+    evict_idle_searchers(state).await;
+    let vault = state.vault().await;
+    let palettes = vault.palettes.read().await;
+    let results = palettes.search(id, &search, range.clone()).await?;
+    if let (Some(window), Some(SearchResults { running: true, .. })) = (window, &results) {
+        let matched = palettes.matched(id, search.clone(), range.clone());
+        let state = state.clone();
+        let window = window.to_owned();
+        tokio::spawn(async move {
+            let results = match matched.await {
+                Ok(Some(results)) => results,
+                Ok(None) => return,
+                Err(err) => {
+                    println!("failed to finish palette search: {err}");
+                    return;
+                }
+            };
+            let event = Event::PaletteResults(PaletteResults {
+                id,
+                search,
+                start: range.start,
+                end: range.end,
+                results,
+            });
+            if let Err(err) = state.event_manager.lock().await.send_event(&window, event) {
+                println!("failed to send palette results: {err}");
+            }
+        });
+    }
+    Ok(results)
 }
 
 pub async fn delete_palette(state: &AppState, id: SearcherId) {
-    let mut palettes = state.palettes.write().await;
+    let vault = state.vault().await;
+    let mut palettes = vault.palettes.write().await;
     palettes.delete_searcher(id);
 }
@@ -9,28 +9,108 @@ use ts_rs::TS;
 use crate::{
     message::{
         action::{read_actions, PartialAction, PartialActionFilter, PartialActionGenerator},
-        meta::read_meta,
+        meta::{read_meta, MetaHolder},
+        note_query::NoteQuery,
+        property::list_property,
+        searcher::SearchField,
         title::path_to_title,
     },
     state::AppState,
 };
 
-#[derive(Serialize, Deserialize, TS, Clone)]
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
 #[ts(export)]
 #[serde(rename_all = "camelCase")]
 pub struct Matched<T> {
+    /// positions of the matched characters in the field the item is shown by
     pub indices: Vec<u32>,
+    /// every field the search matched, with the positions of the matched characters in it
+    pub fields: Vec<FieldMatch>,
     pub payload: T,
 }
 
-#[derive(Serialize, Deserialize, TS, Clone)]
+/// A page of a searcher's results
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
 #[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults<T> {
+    pub matches: Vec<Matched<T>>,
+    /// how many items matched in total
+    pub count: u32,
+    /// whether nucleo is still matching, in which case searching again can find more. The
+    /// window that searched a palette is sent its complete results once nucleo is done.
+    pub running: bool,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldMatch {
+    pub field: String,
+    pub indices: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq, Eq, Hash)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
 pub struct PaletteAction {
     pub title: String,
     pub path: Option<String>,
     pub icon: Option<String>,
     pub shortcut: Option<String>,
     pub action: PartialAction,
+    /// tags of the note the action is about, like "-project--alpha"
+    pub tags: Vec<String>,
+    /// aliases of those tags
+    pub tag_aliases: Vec<String>,
+    /// aliases the note declares in its front matter
+    pub aliases: Vec<String>,
+}
+
+/// What palettes search. Titles, tags and note aliases are searched by default, and
+/// `n:`, `t:`, `a:`, `p:` and `k:` search only the title, tags, note aliases, note path or
+/// action key, like `t:-work k:goto`. Tags are also found by their aliases.
+pub fn palette_search_fields() -> Vec<SearchField<PaletteAction>> {
+    vec![
+        SearchField {
+            name: "title",
+            scope: "n",
+            weight: 1.0,
+            unscoped: true,
+            get: |action| action.title.clone(),
+        },
+        SearchField {
+            name: "tags",
+            scope: "t",
+            weight: 0.8,
+            unscoped: true,
+            get: |action| {
+                let tags = action.tags.iter().chain(&action.tag_aliases);
+                tags.cloned().collect::<Vec<_>>().join(" ")
+            },
+        },
+        SearchField {
+            name: "aliases",
+            scope: "a",
+            weight: 0.6,
+            unscoped: true,
+            get: |action| action.aliases.join(" "),
+        },
+        SearchField {
+            name: "path",
+            scope: "p",
+            weight: 0.5,
+            unscoped: false,
+            get: |action| action.path.clone().unwrap_or_default(),
+        },
+        SearchField {
+            name: "key",
+            scope: "k",
+            weight: 0.5,
+            unscoped: false,
+            get: |action| action.action.key.clone(),
+        },
+    ]
 }
 
 pub async fn get_palette_actions(
@@ -93,7 +173,42 @@ async fn get_all_palette_actions(
         .await
         .into_iter()
         .collect::<Result<Vec<_>>>()?;
-    Ok(unflattened_palette_actions.into_iter().flatten().collect())
+    let mut palette_actions: Vec<_> = unflattened_palette_actions.into_iter().flatten().collect();
+    read_meta(state, |holder| {
+        for palette_action in &mut palette_actions {
+            if let Some(path) = &palette_action.path {
+                (palette_action.tags, palette_action.tag_aliases) = note_tag_names(holder, path);
+                palette_action.aliases = list_property(holder.properties().get(path), "aliases")
+                    .into_iter()
+                    .map(|alias| alias.trim().to_owned())
+                    .filter(|alias| !alias.is_empty())
+                    .collect();
+            }
+        }
+    })
+    .await?;
+    Ok(palette_actions)
+}
+
+/// The tags of a note and the aliases of those tags, written like in file names
+fn note_tag_names(holder: &MetaHolder, path: &str) -> (Vec<String>, Vec<String>) {
+    let tags = holder.note_tags(path);
+    let mut aliases: Vec<String> = holder
+        .tag_resolver()
+        .aliases()
+        .iter()
+        .filter(|(_, canonical)| tags.iter().any(|tag| tag.starts_with(canonical)))
+        .map(|(alias, _)| format!("-{}", alias.join("--")))
+        .collect();
+    aliases.sort();
+    aliases.dedup();
+    let mut tags: Vec<String> = tags
+        .into_iter()
+        .map(|parts| format!("-{}", parts.join("--")))
+        .collect();
+    tags.sort();
+    tags.dedup();
+    (tags, aliases)
 }
 
 async fn generate_palette_actions(
@@ -103,8 +218,13 @@ async fn generate_palette_actions(
     icon: Option<String>,
     generator: PartialActionGenerator,
 ) -> Result<Vec<PaletteAction>> {
+    let query = generator
+        .query
+        .as_deref()
+        .map(NoteQuery::parse)
+        .transpose()?;
     let palette_actions: Vec<_> = if title.contains("$note_locater") {
-        get_all_note_paths(state)
+        get_all_note_paths(state, query.as_ref())
             .await?
             .into_iter()
             .map(|(path, title_replace)| {
@@ -126,7 +246,7 @@ async fn generate_palette_actions(
             })
             .collect()
     } else if title.contains("$note_path") {
-        get_all_note_paths(state)
+        get_all_note_paths(state, query.as_ref())
             .await?
             .into_iter()
             .map(|(path, title_replace)| {
@@ -146,6 +266,34 @@ async fn generate_palette_actions(
                 )
             })
             .collect()
+    } else if title.contains("$vault_name") {
+        let vault_names: Vec<String> = state
+            .settings
+            .lock()
+            .await
+            .vaults
+            .iter()
+            .map(|vault| vault.name.clone())
+            .collect();
+        vault_names
+            .into_iter()
+            .map(|name| {
+                let mut args = generator.args.clone();
+                let index = args.iter().position(|a| a == "$vault_name");
+                if let Some(index) = index {
+                    args[index] = name.clone();
+                }
+                (
+                    title.replace("$vault_name", &name),
+                    icon.clone(),
+                    PartialAction {
+                        key: generator.key.clone(),
+                        args,
+                    },
+                    None,
+                )
+            })
+            .collect()
     } else {
         vec![(
             title,
@@ -165,18 +313,111 @@ async fn generate_palette_actions(
             icon,
             action,
             path,
+            tags: Vec::new(),
+            tag_aliases: Vec::new(),
+            aliases: Vec::new(),
         })
         .collect())
 }
 
-async fn get_all_note_paths(state: &AppState) -> Result<Vec<(String, String)>> {
+async fn get_all_note_paths(
+    state: &AppState,
+    query: Option<&NoteQuery>,
+) -> Result<Vec<(String, String)>> {
     read_meta(state, |holder| {
+        let query = query.map(|query| query.resolve_tags(holder.tag_resolver()));
         holder
             .meta()
             .notes
             .iter()
             .map(|(path, _)| (path.clone(), path_to_title(path)))
+            .filter(|(path, title)| {
+                query.as_ref().is_none_or(|query| {
+                    query.matches(&title.to_lowercase(), &holder.note_tags(path))
+                })
+            })
             .collect()
     })
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{action::ACTIONS_PATH, searcher::SearcherManager};
+
+    const ACTIONS: &str = r#"
+[shortcuts]
+"cmd+o" = "openSettings"
+
+[palettes.main]
+"open $note_path" = { key = "openNote", args = ["$note_path"] }
+"!gear settings" = "openSettings"
+"#;
+
+    #[tokio::test]
+    async fn note_actions_carry_the_note_tags() {
+        let state = AppState::in_memory(&[
+            ("plan_-work.md", "#project/alpha"),
+            ("other.md", ""),
+            (ACTIONS_PATH, ACTIONS),
+        ]);
+        let mut actions = get_palette_actions(&state, "main", Vec::new())
+            .await
+            .unwrap();
+        actions.sort_by(|a, b| a.title.cmp(&b.title));
+
+        let titles: Vec<&str> = actions.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, ["open other", "open plan -work", "settings"]);
+        assert_eq!(actions[1].path.as_deref(), Some("plan_-work.md"));
+        assert_eq!(actions[1].action.args, ["plan_-work.md"]);
+        assert_eq!(actions[1].tags, ["-project--alpha", "-work"]);
+        assert_eq!(actions[2].icon.as_deref(), Some("gear"));
+        assert_eq!(actions[2].shortcut.as_deref(), Some("cmd+o"));
+    }
+
+    #[tokio::test]
+    async fn filters_leave_out_matching_actions() {
+        let state = AppState::in_memory(&[("a.md", ""), (ACTIONS_PATH, ACTIONS)]);
+        let filter = PartialActionFilter {
+            key: "openNote".to_owned(),
+            args: vec![None],
+        };
+        let actions = get_palette_actions(&state, "main", vec![filter])
+            .await
+            .unwrap();
+        let titles: Vec<&str> = actions.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, ["settings"]);
+    }
+
+    #[tokio::test]
+    async fn the_alias_scope_searches_front_matter_aliases() {
+        let state = AppState::in_memory(&[
+            ("plan.md", "---\naliases: [roadmap]\n---\n"),
+            ("road_trip.md", ""),
+            (ACTIONS_PATH, ACTIONS),
+        ]);
+        let actions = get_palette_actions(&state, "main", Vec::new())
+            .await
+            .unwrap();
+        let mut manager = SearcherManager::new(palette_search_fields());
+        let id = manager.new_searcher(&actions, None, None);
+        let results = loop {
+            let results = manager
+                .search(id, "a:roadmap", 0..10)
+                .await
+                .unwrap()
+                .unwrap();
+            if !results.running {
+                break results;
+            }
+        };
+        let titles: Vec<&str> = results
+            .matches
+            .iter()
+            .map(|matched| matched.payload.title.as_str())
+            .collect();
+        assert_eq!(titles, ["open plan"]);
+        assert_eq!(results.matches[0].fields[0].field, "aliases");
+    }
+}
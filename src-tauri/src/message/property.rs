@@ -0,0 +1,334 @@
+use std::cmp::Ordering;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use ts_rs::TS;
+
+use crate::{
+    message::{meta::read_meta, title::path_to_title},
+    state::AppState,
+};
+
+/// Key-values a note declares in its front matter
+pub type Properties = Map<String, Value>;
+
+/// Prefix of the properties every note has without declaring them, like `file.modified`
+const FILE_PREFIX: &str = "file.";
+
+/// Splits a note into the yaml of the front matter at its very top, delimited by `---` lines,
+/// and the rest of the note
+fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+    let mut lines = content.split_inclusive('\n');
+    let first = lines.next()?;
+    if first.trim_end() != "---" {
+        return None;
+    }
+    let start = first.len();
+    let mut end = start;
+    for line in lines {
+        if matches!(line.trim_end(), "---" | "...") {
+            return Some((&content[start..end], &content[end + line.len()..]));
+        }
+        end += line.len();
+    }
+    None
+}
+
+/// Parses the yaml front matter of a note. Returns None if the note has no front matter or it
+/// isn't a yaml mapping.
+pub fn parse_front_matter(content: &str) -> Option<Properties> {
+    let (yaml, _) = split_front_matter(content)?;
+    match serde_yaml::from_str::<Value>(yaml) {
+        Ok(Value::Object(properties)) => Some(properties),
+        Ok(_) => None,
+        Err(err) => {
+            println!("invalid front matter: {err}");
+            None
+        }
+    }
+}
+
+/// The part of a note after its front matter
+pub fn note_body(content: &str) -> &str {
+    split_front_matter(content).map_or(content, |(_, body)| body)
+}
+
+/// The items of a list property like `tags` or `aliases`, which can be a yaml list or a comma
+/// separated string
+pub fn list_property<'a>(properties: Option<&'a Properties>, key: &str) -> Vec<&'a str> {
+    match properties.and_then(|properties| properties.get(key)) {
+        Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).collect(),
+        Some(Value::String(list)) => list
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyQuery {
+    /// notes have to match every filter
+    #[serde(default)]
+    pub filters: Vec<PropertyFilter>,
+    /// sorts by the first entry, then the next for notes that are equal, and so on
+    #[serde(default)]
+    pub sort: Vec<PropertySort>,
+    /// properties that become the columns of the table. Besides front matter properties,
+    /// `file.path`, `file.title` and the fields of NoteMeta like `file.modified` can be used.
+    pub select: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyFilter {
+    pub property: String,
+    pub op: FilterOp,
+    /// value to compare against, unused by `exists` and `missing`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional, type = "unknown")]
+    pub value: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterOp {
+    Exists,
+    Missing,
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    /// a list containing the value, or a string containing it as a substring
+    Contains,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertySort {
+    pub property: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<PropertyRow>,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyRow {
+    pub path: String,
+    /// one value per column, null where the note doesn't have the property
+    #[ts(type = "Array<unknown>")]
+    pub values: Vec<Value>,
+}
+
+/// Orders json values of the same kind, numbers numerically and strings (which includes
+/// dates) lexicographically. Values of different kinds can't be compared.
+fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+impl PropertyFilter {
+    fn matches(&self, value: Option<&Value>) -> bool {
+        let compare = || {
+            value
+                .zip(self.value.as_ref())
+                .and_then(|(value, expected)| compare_values(value, expected))
+        };
+        match self.op {
+            FilterOp::Exists => value.is_some(),
+            FilterOp::Missing => value.is_none(),
+            FilterOp::Eq => value.is_some() && value == self.value.as_ref(),
+            FilterOp::Ne => value != self.value.as_ref(),
+            FilterOp::Lt => compare() == Some(Ordering::Less),
+            FilterOp::Lte => matches!(compare(), Some(Ordering::Less | Ordering::Equal)),
+            FilterOp::Gt => compare() == Some(Ordering::Greater),
+            FilterOp::Gte => matches!(compare(), Some(Ordering::Greater | Ordering::Equal)),
+            FilterOp::Contains => match (value, &self.value) {
+                (Some(Value::Array(items)), Some(expected)) => items.contains(expected),
+                (Some(Value::String(s)), Some(Value::String(expected))) => s.contains(expected),
+                _ => false,
+            },
+        }
+    }
+}
+
+/// Looks up a property of a note, where `file.` properties come from its path and NoteMeta
+fn lookup(
+    path: &str,
+    note_meta: &Map<String, Value>,
+    properties: Option<&Properties>,
+    property: &str,
+) -> Option<Value> {
+    if let Some(field) = property.strip_prefix(FILE_PREFIX) {
+        return match field {
+            "path" => Some(path.into()),
+            "title" => Some(path_to_title(path).into()),
+            field => note_meta.get(field).cloned(),
+        };
+    }
+    properties?.get(property).cloned()
+}
+
+/// Runs a query over the front matter of every note in the vault
+pub async fn query_properties(state: &AppState, query: PropertyQuery) -> Result<PropertyTable> {
+    let mut rows: Vec<(String, Vec<Option<Value>>, Vec<Option<Value>>)> =
+        read_meta(state, |holder| {
+            holder
+                .meta()
+                .notes
+                .iter()
+                .filter_map(|(path, note_meta)| {
+                    let Ok(Value::Object(note_meta)) = serde_json::to_value(note_meta) else {
+                        return None;
+                    };
+                    let properties = holder.properties().get(path);
+                    let get = |property: &str| lookup(path, &note_meta, properties, property);
+                    let matches = query
+                        .filters
+                        .iter()
+                        .all(|filter| filter.matches(get(&filter.property).as_ref()));
+                    matches.then(|| {
+                        (
+                            path.clone(),
+                            query.sort.iter().map(|sort| get(&sort.property)).collect(),
+                            query.select.iter().map(|property| get(property)).collect(),
+                        )
+                    })
+                })
+                .collect()
+        })
+        .await?;
+
+    rows.sort_by(|(a_path, a_keys, _), (b_path, b_keys, _)| {
+        for (sort, (a, b)) in query.sort.iter().zip(a_keys.iter().zip(b_keys)) {
+            // notes without the property always go last
+            let ordering = match (a, b) {
+                (Some(a), Some(b)) => {
+                    let ordering = compare_values(a, b).unwrap_or(Ordering::Equal);
+                    if sort.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                }
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        a_path.cmp(b_path)
+    });
+
+    Ok(PropertyTable {
+        columns: query.select,
+        rows: rows
+            .into_iter()
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|(path, _, values)| PropertyRow {
+                path,
+                values: values
+                    .into_iter()
+                    .map(|value| value.unwrap_or(Value::Null))
+                    .collect(),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn front_matter_has_to_be_a_closed_mapping_at_the_top() {
+        assert_eq!(parse_front_matter("just a note\n---\na: 1\n---\n"), None);
+        assert_eq!(parse_front_matter("---\na: 1\nno end"), None);
+        assert_eq!(parse_front_matter("---\n- a\n- b\n---\n"), None);
+        assert_eq!(parse_front_matter("---\n[unclosed\n---\n"), None);
+
+        let content = "---\nstatus: done\nrating: 4\n...\nbody\n";
+        let properties = parse_front_matter(content).unwrap();
+        assert_eq!(properties["status"], json!("done"));
+        assert_eq!(properties["rating"], json!(4));
+        assert_eq!(note_body(content), "body\n");
+        assert_eq!(note_body("---\nno end"), "---\nno end");
+    }
+
+    #[test]
+    fn list_properties_can_be_lists_or_strings() {
+        let properties =
+            parse_front_matter("---\ntags: [ml, ai]\naliases: one, two\nsingle: x\n---\n");
+        let properties = properties.as_ref();
+        assert_eq!(list_property(properties, "tags"), ["ml", "ai"]);
+        assert_eq!(list_property(properties, "aliases"), ["one", "two"]);
+        assert_eq!(list_property(properties, "single"), ["x"]);
+        assert!(list_property(properties, "missing").is_empty());
+        assert!(list_property(None, "tags").is_empty());
+    }
+
+    #[tokio::test]
+    async fn queries_filter_sort_and_select() {
+        let state = AppState::in_memory(&[
+            ("a.md", "---\nstatus: done\nrating: 2\n---\n"),
+            ("b.md", "---\nstatus: done\nrating: 5\n---\n"),
+            ("c.md", "---\nstatus: todo\nrating: 9\n---\n"),
+            ("d.md", "no front matter"),
+        ]);
+        let query = PropertyQuery {
+            filters: vec![PropertyFilter {
+                property: "status".to_owned(),
+                op: FilterOp::Eq,
+                value: Some(json!("done")),
+            }],
+            sort: vec![PropertySort {
+                property: "rating".to_owned(),
+                descending: true,
+            }],
+            select: vec!["file.title".to_owned(), "rating".to_owned()],
+            limit: None,
+        };
+        let table = query_properties(&state, query).await.unwrap();
+        let rows: Vec<(&str, &[Value])> = table
+            .rows
+            .iter()
+            .map(|row| (row.path.as_str(), row.values.as_slice()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("b.md", &[json!("b"), json!(5)][..]),
+                ("a.md", &[json!("a"), json!(2)][..]),
+            ]
+        );
+    }
+}
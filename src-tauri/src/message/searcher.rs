@@ -1,66 +1,243 @@
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use nucleo::{
-    pattern::{CaseMatching, Normalization},
+    pattern::{CaseMatching, MultiPattern, Normalization},
     Nucleo, Utf32String,
 };
-use nucleo::{Matcher, Utf32Str};
+use nucleo::{Item, Matcher};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use ts_rs::TS;
 
-use crate::message::palette_action::Matched;
+use crate::message::palette_action::{FieldMatch, Matched, SearchResults};
+use crate::state::AppState;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, TS)]
 #[ts(export, type = "number & { readonly __tag: unique symbol }")]
 pub struct SearcherId(usize);
-pub struct SearcherManager<T: Clone + Send + Sync + 'static> {
-    palettes: HashMap<SearcherId, Arc<Mutex<Searcher<T>>>>,
-    id_counter: usize,
-    get_action_search: fn(&T) -> String,
+
+/// Ids are unique across all managers, so an id from one vault never finds a searcher of another
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// How long a searcher can go unused before it is dropped, in case its window forgot it
+const IDLE_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Most searchers a manager keeps, past which the least recently used one is dropped
+const MAX_SEARCHERS: usize = 32;
+
+/// How long nucleo is given to finish matching at a time while waiting for it, in milliseconds
+const MATCH_WAIT_MS: u64 = 50;
+
+/// Decides the order of a searcher's results, instead of nucleo's fuzzy score alone
+pub trait RankingPolicy<T>: Send + Sync {
+    /// Score of an item that matched the search with nucleo's `match_score`, higher first
+    fn score(&self, item: &T, match_score: u32) -> f64;
+    /// When the item was last used, which orders the items when nothing is searched. Items
+    /// that were never used come last, in their original order.
+    fn recency(&self, item: &T) -> Option<u64>;
+}
+
+/// A part of the items a searcher searches, like the title of a palette action
+pub struct SearchField<T> {
+    /// what the field is called in [`FieldMatch`]
+    pub name: &'static str,
+    /// prefix that searches only this field, like `t` in `t:-work`
+    pub scope: &'static str,
+    /// how much a match in this field counts compared to a match in the others
+    pub weight: f64,
+    /// whether words without a scope search this field
+    pub unscoped: bool,
+    pub get: fn(&T) -> String,
+}
+
+struct SearcherEntry<T: Clone + Eq + Hash + Send + Sync + 'static> {
+    searcher: Arc<Mutex<Searcher<T>>>,
+    /// label of the window that created the searcher, which it is dropped along with
+    window: Option<String>,
+    created: Instant,
+    last_used: std::sync::Mutex<Instant>,
+    /// task that keeps the searcher's items up to date, which is stopped along with it
+    task: Option<JoinHandle<()>>,
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync + 'static> Drop for SearcherEntry<T> {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+/// A live searcher, for debugging leaks
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct SearcherInfo {
+    pub id: SearcherId,
+    /// label of the window that created the searcher
+    pub window: Option<String>,
+    #[ts(type = "number")]
+    pub item_count: usize,
+    /// rough size of the items and their search columns in bytes
+    #[ts(type = "number")]
+    pub memory: usize,
+    #[ts(type = "number")]
+    pub age_secs: u64,
+    #[ts(type = "number")]
+    pub idle_secs: u64,
+}
+
+pub struct SearcherManager<T: Clone + Eq + Hash + Send + Sync + 'static> {
+    palettes: HashMap<SearcherId, SearcherEntry<T>>,
+    fields: Arc<[SearchField<T>]>,
+    /// how many of nucleo's best matches are scored again, see [`Self::with_scored_matches`]
+    scored_matches: Option<usize>,
 }
 
-impl<T: Clone + Send + Sync + 'static> SearcherManager<T> {
-    pub fn new(get_action_search: fn(&T) -> String) -> Self {
+impl<T: Clone + Eq + Hash + Send + Sync + 'static> SearcherManager<T> {
+    /// The first field is the one the items are shown by
+    pub fn new(fields: Vec<SearchField<T>>) -> Self {
         Self {
             palettes: HashMap::new(),
-            id_counter: 0,
-            get_action_search,
+            fields: fields.into(),
+            scored_matches: None,
         }
     }
 
+    /// Every match is scored again with the field weights and the ranking policy on each
+    /// search. With a limit, only that many of nucleo's best matches are, or as many as the
+    /// requested range needs, and the rest keep nucleo's unweighted order. That makes searching
+    /// many items cheaper, but a match nucleo ranks past the limit can't be moved up anymore.
+    pub fn with_scored_matches(mut self, limit: usize) -> Self {
+        self.scored_matches = Some(limit);
+        self
+    }
+
     fn new_id(&mut self) -> SearcherId {
-        let id = SearcherId(self.id_counter);
-        self.id_counter += 1;
-        id
+        SearcherId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
     }
 
-    pub fn new_searcher(&mut self, actions: &[T]) -> SearcherId {
+    /// Creates a searcher over `actions`, owned by the window labeled `window` if there is one
+    pub fn new_searcher(
+        &mut self,
+        actions: &[T],
+        ranking: Option<Box<dyn RankingPolicy<T>>>,
+        window: Option<&str>,
+    ) -> SearcherId {
+        self.make_room();
         let id = self.new_id();
+        let searcher = Searcher::new(actions, self.fields.clone(), ranking, self.scored_matches);
+        let now = Instant::now();
         self.palettes.insert(
             id,
-            Arc::new(Mutex::new(Searcher::new(actions, self.get_action_search))),
+            SearcherEntry {
+                searcher: Arc::new(Mutex::new(searcher)),
+                window: window.map(str::to_owned),
+                created: now,
+                last_used: std::sync::Mutex::new(now),
+                task: None,
+            },
         );
         id
     }
 
+    /// Drops searchers that have been idle for too long
+    pub fn evict_idle(&mut self) {
+        self.palettes
+            .retain(|_, entry| entry.last_used.lock().unwrap().elapsed() < IDLE_TTL);
+    }
+
+    /// Drops idle searchers, then the least recently used ones until there is room for another
+    fn make_room(&mut self) {
+        self.evict_idle();
+        let last_used = |entry: &SearcherEntry<T>| *entry.last_used.lock().unwrap();
+        while self.palettes.len() >= MAX_SEARCHERS {
+            let Some(oldest) = self
+                .palettes
+                .iter()
+                .min_by_key(|(_, entry)| last_used(entry))
+                .map(|(id, _)| *id)
+            else {
+                break;
+            };
+            self.palettes.remove(&oldest);
+        }
+    }
+
+    /// Makes `task` stop when the searcher is dropped, or right away if there is no searcher
+    /// with the id
+    pub fn set_task(&mut self, id: SearcherId, task: JoinHandle<()>) {
+        match self.palettes.get_mut(&id) {
+            Some(entry) => entry.task = Some(task),
+            None => task.abort(),
+        }
+    }
+
     pub fn delete_searcher(&mut self, id: SearcherId) {
         self.palettes.remove(&id);
     }
 
+    /// Drops every searcher the window labeled `window` created
+    pub fn delete_window_searchers(&mut self, window: &str) {
+        self.palettes
+            .retain(|_, entry| entry.window.as_deref() != Some(window));
+    }
+
+    pub fn contains(&self, id: SearcherId) -> bool {
+        self.palettes.contains_key(&id)
+    }
+
+    /// The live searchers, after dropping the idle ones
+    pub async fn searchers(&mut self) -> Vec<SearcherInfo> {
+        self.evict_idle();
+        let mut searchers = Vec::new();
+        for (id, entry) in &self.palettes {
+            let searcher = entry.searcher.lock().await;
+            searchers.push(SearcherInfo {
+                id: *id,
+                window: entry.window.clone(),
+                item_count: searcher.live.len(),
+                memory: searcher.memory,
+                age_secs: entry.created.elapsed().as_secs(),
+                idle_secs: entry.last_used.lock().unwrap().elapsed().as_secs(),
+            });
+        }
+        searchers.sort_by_key(|searcher| searcher.id);
+        searchers
+    }
+
+    /// Replaces the items of a searcher, keeping what nucleo already matched. Returns false if
+    /// there is no searcher with the id.
+    pub async fn update_searcher(&self, id: SearcherId, actions: Vec<T>) -> Result<bool> {
+        let Some(entry) = self.palettes.get(&id) else {
+            return Ok(false);
+        };
+        let palette = entry.searcher.clone();
+        tokio::task::spawn_blocking(move || palette.blocking_lock().update(actions))
+            .await
+            .map_err(|e| anyhow::anyhow!("task panicked: {}", e))?;
+        Ok(true)
+    }
+
     pub async fn search(
         &self,
         id: SearcherId,
         search: &str,
         range: Range<u32>,
-    ) -> Result<Option<Vec<Matched<T>>>> {
-        let Some(palette) = self.palettes.get(&id) else {
+    ) -> Result<Option<SearchResults<T>>> {
+        let Some(entry) = self.palettes.get(&id) else {
             return Ok(None);
         };
-        let palette = palette.clone();
+        *entry.last_used.lock().unwrap() = Instant::now();
+        let palette = entry.searcher.clone();
         let search = search.to_string();
         let range = range.clone();
 
@@ -73,73 +250,423 @@ impl<T: Clone + Send + Sync + 'static> SearcherManager<T> {
 
         Ok(Some(result))
     }
+
+    /// Waits until nucleo is done matching `search`, then returns the `range` of its results.
+    /// Returns None if there is no searcher with the id, or it was searched for something else
+    /// in the meantime.
+    pub fn matched(
+        &self,
+        id: SearcherId,
+        search: String,
+        range: Range<u32>,
+    ) -> impl Future<Output = Result<Option<SearchResults<T>>>> + use<T> {
+        let palette = self.palettes.get(&id).map(|entry| entry.searcher.clone());
+        async move {
+            let Some(palette) = palette else {
+                return Ok(None);
+            };
+            tokio::task::spawn_blocking(move || loop {
+                let mut palette = palette.blocking_lock();
+                if palette.last_search != search {
+                    return None;
+                }
+                let status = palette.nucleo.tick(MATCH_WAIT_MS);
+                if !status.running {
+                    return Some(palette.results(range.clone(), false));
+                }
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("task panicked: {}", e))
+        }
+    }
+}
+
+/// The live searchers of a vault
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultSearchers {
+    pub vault: String,
+    pub palettes: Vec<SearcherInfo>,
+    pub suggesters: Vec<SearcherInfo>,
+}
+
+/// Drops the searchers the window labeled `window` created in any vault, for when it closes
+/// or reloads and can't delete them itself
+pub async fn delete_window_searchers(state: &AppState, window: &str) {
+    let vaults: Vec<_> = state.vaults.lock().await.values().cloned().collect();
+    for vault in vaults {
+        vault.palettes.write().await.delete_window_searchers(window);
+        vault
+            .suggesters
+            .write()
+            .await
+            .delete_window_searchers(window);
+    }
+}
+
+/// Drops the searchers that have been idle for too long in any vault. Searching is what keeps
+/// a searcher alive, so it is also when the idle ones are dropped.
+pub async fn evict_idle_searchers(state: &AppState) {
+    let vaults: Vec<_> = state.vaults.lock().await.values().cloned().collect();
+    for vault in vaults {
+        vault.palettes.write().await.evict_idle();
+        vault.suggesters.write().await.evict_idle();
+    }
 }
 
-struct Searcher<T: Clone + Send + Sync + 'static> {
+pub async fn list_searchers(state: &AppState) -> Vec<VaultSearchers> {
+    let vaults: Vec<_> = state
+        .vaults
+        .lock()
+        .await
+        .iter()
+        .map(|(name, vault)| (name.clone(), vault.clone()))
+        .collect();
+    let mut searchers = Vec::new();
+    for (name, vault) in vaults {
+        searchers.push(VaultSearchers {
+            vault: name,
+            palettes: vault.palettes.write().await.searchers().await,
+            suggesters: vault.suggesters.write().await.searchers().await,
+        });
+    }
+    searchers.sort_by(|a, b| a.vault.cmp(&b.vault));
+    searchers
+}
+
+/// Splits a search into a pattern for the column of each field, followed by the pattern for
+/// the words without a scope
+fn split_search<T>(fields: &[SearchField<T>], search: &str) -> Vec<String> {
+    let mut patterns = vec![Vec::new(); fields.len() + 1];
+    for word in search.split_whitespace() {
+        let scoped = word.split_once(':').and_then(|(scope, rest)| {
+            let column = fields.iter().position(|field| field.scope == scope)?;
+            Some((column, rest))
+        });
+        match scoped {
+            // a scope that is still being typed doesn't filter anything yet
+            Some((_, "")) => {}
+            Some((column, rest)) => patterns[column].push(rest),
+            None => patterns[fields.len()].push(word),
+        }
+    }
+    patterns.into_iter().map(|words| words.join(" ")).collect()
+}
+
+/// Scores an item against every column with a pattern, weighting each match by its field.
+/// `columns` are the texts nucleo keeps for the item. Returns the score along with the
+/// matched characters in each field.
+fn match_fields<T>(
+    fields: &[SearchField<T>],
+    pattern: &MultiPattern,
+    active: &[bool],
+    matcher: &mut Matcher,
+    columns: &[Utf32String],
+) -> (f64, Vec<Vec<u32>>) {
+    let mut score = 0.0;
+    let mut indices = vec![Vec::new(); fields.len()];
+    for (column, field) in fields.iter().enumerate() {
+        if !active[column] {
+            continue;
+        }
+        if let Some(field_score) = pattern.column_pattern(column).indices(
+            columns[column].slice(..),
+            matcher,
+            &mut indices[column],
+        ) {
+            score += field_score as f64 * field.weight;
+        }
+    }
+    if active[fields.len()] {
+        let mut joined_indices = Vec::new();
+        if let Some(joined_score) = pattern.column_pattern(fields.len()).indices(
+            columns[fields.len()].slice(..),
+            matcher,
+            &mut joined_indices,
+        ) {
+            // the match counts with the weights of the fields its characters are in
+            let mut weight = 0.0;
+            let mut offset = 0;
+            for (column, field) in fields.iter().enumerate().filter(|(_, f)| f.unscoped) {
+                let len = columns[column].len() as u32;
+                for &index in &joined_indices {
+                    if (offset..offset + len).contains(&index) {
+                        indices[column].push(index - offset);
+                        weight += field.weight;
+                    }
+                }
+                offset += len + 1;
+            }
+            let weight = if joined_indices.is_empty() {
+                1.0
+            } else {
+                weight / joined_indices.len() as f64
+            };
+            score += joined_score as f64 * weight;
+        }
+    }
+    for field_indices in &mut indices {
+        field_indices.sort_unstable();
+        field_indices.dedup();
+    }
+    (score, indices)
+}
+
+struct Searcher<T: Clone + Eq + Hash + Send + Sync + 'static> {
     nucleo: Nucleo<T>,
     matcher: Matcher,
-    last_search: Option<String>,
-    get_action_search: fn(&T) -> String,
+    /// the last search, and the pattern of each column in it
+    last_search: String,
+    last_patterns: Vec<String>,
+    fields: Arc<[SearchField<T>]>,
+    ranking: Option<Box<dyn RankingPolicy<T>>>,
+    scored_matches: Option<usize>,
+    /// every item that is in nucleo, including retracted ones since nucleo can't remove items
+    injected: HashSet<T>,
+    /// the items that can still be matched
+    live: HashSet<T>,
+    /// rough size of the items and their search columns in bytes
+    memory: usize,
 }
 
-impl<T: Clone + Send + Sync + 'static> Searcher<T> {
-    fn new(actions: &[T], get_action_search: fn(&T) -> String) -> Self {
+impl<T: Clone + Eq + Hash + Send + Sync + 'static> Searcher<T> {
+    fn new(
+        actions: &[T],
+        fields: Arc<[SearchField<T>]>,
+        ranking: Option<Box<dyn RankingPolicy<T>>>,
+        scored_matches: Option<usize>,
+    ) -> Self {
         let config = nucleo::Config::DEFAULT;
-        let nucleo = Nucleo::new(config.clone(), Arc::new(|| {}), None, 1);
+        // a column for each field, and one for the words without a scope
+        let columns = fields.len() + 1;
+        let nucleo = Nucleo::new(config.clone(), Arc::new(|| {}), None, columns as u32);
         let matcher = Matcher::new(config.clone());
 
-        let injector = nucleo.injector();
-        for action in actions {
-            injector.push(action.clone(), |action, strings| {
-                strings[0] = Utf32String::from(get_action_search(action));
-            });
-        }
-
-        Self {
+        let mut searcher = Self {
             nucleo,
             matcher,
-            last_search: None,
-            get_action_search,
+            last_search: String::new(),
+            last_patterns: vec![String::new(); columns],
+            fields,
+            ranking,
+            scored_matches,
+            injected: HashSet::new(),
+            live: HashSet::new(),
+            memory: 0,
+        };
+        searcher.update(actions.to_vec());
+        searcher
+    }
+
+    fn inject(&mut self, action: T) {
+        if !self.injected.insert(action.clone()) {
+            return;
         }
+        let fields = &self.fields;
+        let mut memory = std::mem::size_of::<T>();
+        self.nucleo.injector().push(action, |action, strings| {
+            let mut unscoped = Vec::new();
+            for (column, field) in fields.iter().enumerate() {
+                let text = (field.get)(action);
+                memory += text.len();
+                if field.unscoped {
+                    unscoped.push(text.clone());
+                }
+                strings[column] = Utf32String::from(text);
+            }
+            // the words without a scope search the unscoped fields joined by spaces
+            let joined = unscoped.join(" ");
+            memory += joined.len();
+            strings[fields.len()] = Utf32String::from(joined);
+        });
+        self.memory += memory;
     }
 
-    fn search(&mut self, search: &str, range: Range<u32>) -> Vec<Matched<T>> {
-        let append = self
-            .last_search
-            .as_ref()
-            .is_some_and(|last_search| search.starts_with(last_search));
-        self.last_search = Some(search.to_owned());
-        self.nucleo
-            .pattern
-            .reparse(0, search, CaseMatching::Smart, Normalization::Smart, append);
-        let status = self.nucleo.tick(10);
-        if status.running {
-            println!("palette still running");
+    /// Makes `actions` the items of the searcher, pushing the new ones to nucleo and retracting
+    /// the ones that are gone
+    fn update(&mut self, actions: Vec<T>) {
+        self.live = actions.iter().cloned().collect();
+        // once most of nucleo's items are retracted, it's cheaper to start over
+        if self.injected.len() > 2 * self.live.len() + 64 {
+            self.nucleo.restart(true);
+            self.injected.clear();
+            self.memory = 0;
         }
-        let snapshot = self.nucleo.snapshot();
-        let count = snapshot.matched_item_count();
-        if count == 0 {
-            return Vec::new();
+        for action in actions {
+            self.inject(action);
         }
+    }
+
+    fn search(&mut self, search: &str, range: Range<u32>) -> SearchResults<T> {
+        let patterns = split_search(&self.fields, search);
+        for (column, pattern) in patterns.iter().enumerate() {
+            let append = pattern.starts_with(&self.last_patterns[column]);
+            self.nucleo.pattern.reparse(
+                column,
+                pattern,
+                CaseMatching::Smart,
+                Normalization::Smart,
+                append,
+            );
+        }
+        self.last_search = search.to_owned();
+        self.last_patterns = patterns;
+        let status = self.nucleo.tick(10);
+        self.results(range, status.running)
+    }
+
+    /// The `range` of the results of the last search, in the order nucleo matched them so far
+    fn results(&mut self, range: Range<u32>, running: bool) -> SearchResults<T> {
+        let active: Vec<bool> = self
+            .last_patterns
+            .iter()
+            .map(|pattern| !pattern.is_empty())
+            .collect();
+        let snapshot = self.nucleo.snapshot();
         let pattern = snapshot.pattern();
-        let start = range.start.min(count);
-        let end = range.end.min(count);
-        snapshot
-            .matched_items(start..end)
+        let live = snapshot
+            .matched_items(..)
+            .filter(|item| self.live.contains(item.data));
+        let searching = active.contains(&true);
+        // nucleo already orders the matches by their unweighted score, so with a limit only
+        // the best of them are scored again with the field weights
+        let scored_count = self
+            .scored_matches
+            .map_or(usize::MAX, |limit| limit.max(range.end as usize));
+        let mut count = 0;
+        let mut items = Vec::new();
+        for item in live {
+            if !searching || items.len() < scored_count {
+                items.push(item);
+            }
+            count += 1;
+        }
+        if searching {
+            let mut scored: Vec<(f64, Item<T>)> = items
+                .into_iter()
+                .map(|item| {
+                    let (score, _) = match_fields(
+                        &self.fields,
+                        pattern,
+                        &active,
+                        &mut self.matcher,
+                        item.matcher_columns,
+                    );
+                    let score = match &self.ranking {
+                        Some(ranking) => ranking.score(item.data, score.round() as u32),
+                        None => score,
+                    };
+                    (score, item)
+                })
+                .collect();
+            scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+            items = scored.into_iter().map(|(_, item)| item).collect();
+        } else if let Some(ranking) = &self.ranking {
+            items.sort_by_key(|item| Reverse(ranking.recency(item.data)));
+        }
+        let matches = items
+            .into_iter()
+            .skip(range.start as usize)
+            .take(range.end.saturating_sub(range.start) as usize)
             .map(|item| {
-                let mut haystack_buf = Vec::new();
-                let search = (self.get_action_search)(&item.data);
-                let haystack = Utf32Str::new(&search, &mut haystack_buf);
-                let mut indices = Vec::new();
-                pattern
-                    .column_pattern(0)
-                    .indices(haystack, &mut self.matcher, &mut indices);
+                let (_, indices) = match_fields(
+                    &self.fields,
+                    pattern,
+                    &active,
+                    &mut self.matcher,
+                    item.matcher_columns,
+                );
+                let fields = self
+                    .fields
+                    .iter()
+                    .zip(indices)
+                    .filter(|(_, indices)| !indices.is_empty())
+                    .map(|(field, indices)| FieldMatch {
+                        field: field.name.to_owned(),
+                        indices,
+                    })
+                    .collect::<Vec<_>>();
+                let indices = fields
+                    .iter()
+                    .find(|field_match| field_match.field == self.fields[0].name)
+                    .map(|field_match| field_match.indices.clone())
+                    .unwrap_or_default();
 
                 Matched {
                     indices,
+                    fields,
                     payload: item.data.clone(),
                 }
             })
-            .collect()
+            .collect();
+        SearchResults {
+            matches,
+            count,
+            running,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a title and a path
+    type Pair = (String, String);
+
+    fn fields() -> Vec<SearchField<Pair>> {
+        vec![
+            SearchField {
+                name: "title",
+                scope: "n",
+                weight: 1.0,
+                unscoped: true,
+                get: |pair| pair.0.clone(),
+            },
+            SearchField {
+                name: "path",
+                scope: "p",
+                weight: 0.1,
+                unscoped: true,
+                get: |pair| pair.1.clone(),
+            },
+        ]
+    }
+
+    async fn titles(
+        manager: &SearcherManager<Pair>,
+        id: SearcherId,
+        search: &str,
+        range: Range<u32>,
+    ) -> Vec<String> {
+        loop {
+            let results = manager.search(id, search, range.clone()).await.unwrap();
+            let results = results.unwrap();
+            if !results.running {
+                return results
+                    .matches
+                    .into_iter()
+                    .map(|matched| matched.payload.0)
+                    .collect();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn weighted_fields_outrank_a_better_raw_match() {
+        let pairs = [
+            ("other".to_owned(), "foo".to_owned()),
+            ("fxoxo".to_owned(), "other".to_owned()),
+        ];
+        let mut manager = SearcherManager::new(fields());
+        let id = manager.new_searcher(&pairs, None, None);
+        assert_eq!(titles(&manager, id, "foo", 0..10).await, ["fxoxo", "other"]);
+        assert_eq!(titles(&manager, id, "foo", 0..1).await, ["fxoxo"]);
+
+        // with a limit, the matches nucleo ranks past it keep their unweighted order
+        let mut manager = SearcherManager::new(fields()).with_scored_matches(0);
+        let id = manager.new_searcher(&pairs, None, None);
+        assert_eq!(titles(&manager, id, "foo", 0..1).await, ["other"]);
     }
 }
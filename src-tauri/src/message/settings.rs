@@ -1,50 +1,208 @@
 use std::{
     collections::{HashMap, HashSet},
-    io::Write,
     path::PathBuf,
 };
 
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-use crate::{message::locater::Locater, state::AppState, window_state::WindowState};
+use crate::{
+    message::{
+        locater::Locater,
+        meta::flush_all_meta,
+        store::{backup_path, tmp_path, write_atomic_with_backup},
+    },
+    state::AppState,
+    watcher::restart_watcher,
+    window_state::WindowState,
+};
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 
 #[derive(Serialize, Deserialize, TS, Clone)]
 #[ts(export)]
 #[serde(rename_all = "camelCase")]
 pub struct Settings {
-    pub notes_path: Option<String>,
+    /// only read to migrate settings from before there were multiple vaults
+    #[serde(default, skip_serializing)]
+    #[ts(skip)]
+    notes_path: Option<String>,
+    #[serde(default)]
+    pub vaults: Vec<Vault>,
+    /// name of the vault notes are read from, the first vault is used if it is None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub active_vault: Option<String>,
     pub window_states: HashMap<Locater, WindowState>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[ts(optional)]
     pub minimized_pinned_paths: Option<HashSet<String>>,
+    /// names or relative paths in the notes folder that are left out of the vault
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub ignored_paths: Option<Vec<String>>,
+    /// how many days trashed notes are kept before purging the trash removes them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub trash_retention_days: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct Vault {
+    pub name: String,
+    /// folder the vault's notes are kept in
+    pub path: String,
+}
+
+impl Settings {
+    /// The vault notes are currently read from
+    pub fn active_vault(&self) -> Option<&Vault> {
+        self.active_vault
+            .as_ref()
+            .and_then(|name| self.vaults.iter().find(|vault| vault.name == *name))
+            .or(self.vaults.first())
+    }
+
+    /// Turns the notes folder of old settings into a vault
+    fn migrate_notes_path(mut self) -> Self {
+        if let Some(path) = self.notes_path.take()
+            && self.vaults.is_empty()
+        {
+            let name = PathBuf::from(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "notes".to_owned());
+            self.active_vault = Some(name.clone());
+            self.vaults.push(Vault { name, path });
+        }
+        self
+    }
+}
+
+const SETTINGS_PATH: &str = "settings.json";
+
+fn parse_settings(contents: &str) -> serde_json::Result<Settings> {
+    serde_json::from_str(contents).map(Settings::migrate_notes_path)
 }
 
 pub fn read_settings_file(config_path: &PathBuf) -> Result<Settings> {
-    let path = config_path.join("settings.json");
+    let path = config_path.join(SETTINGS_PATH);
 
     if path.exists() {
-        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
-    } else {
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
+        let err = match parse_settings(&std::fs::read_to_string(&path)?) {
+            Ok(settings) => return Ok(settings),
+            Err(err) => err,
+        };
+        for fallback in [tmp_path(SETTINGS_PATH), backup_path(SETTINGS_PATH)] {
+            if let Ok(contents) = std::fs::read_to_string(config_path.join(&fallback))
+                && let Ok(settings) = parse_settings(&contents)
+            {
+                println!("settings are corrupt ({err}), using {fallback:?} instead");
+                return Ok(settings);
+            }
         }
-        let mut file = std::fs::File::create(path)?;
-        file.write_all(serde_json::to_string(&Settings::default())?.as_bytes())?;
+        Err(err.into())
+    } else {
+        write_settings_file(config_path, &Settings::default())?;
         Ok(Settings::default())
     }
 }
 
+/// Atomically writes the settings file, keeping the previous one as a backup
+pub fn write_settings_file(config_path: &PathBuf, settings: &Settings) -> Result<()> {
+    write_atomic_with_backup(
+        &config_path.join(SETTINGS_PATH),
+        serde_json::to_string(settings)?.as_bytes(),
+    )?;
+    Ok(())
+}
+
 pub async fn write_settings(state: &AppState, settings: Settings) -> Result<()> {
     println!("writing settings");
-    tokio::fs::write(
-        state.config_path.join("settings.json"),
-        serde_json::to_string(&settings).unwrap(),
-    )
-    .await?;
-    (*state.settings.lock().await) = settings;
+    // the android store is the one folder picked through the storage access framework, so
+    // a second vault would share the notes and brot.json of the first
+    #[cfg(target_os = "android")]
+    if settings.vaults.len() > 1 {
+        bail!("android can only have one vault for now");
+    }
+    for (i, vault) in settings.vaults.iter().enumerate() {
+        // vault names end up in locaters like `vault:name:note:path`
+        if vault.name.is_empty() || vault.name.contains(':') {
+            bail!("invalid vault name {:?}", vault.name);
+        }
+        if settings.vaults[..i]
+            .iter()
+            .any(|other| other.name == vault.name)
+        {
+            bail!("there are multiple vaults called {:?}", vault.name);
+        }
+    }
+    // vaults may be moved or removed, so their meta has to be written while they are where it
+    // was read from
+    flush_all_meta(state).await?;
+    let config_path = state.config_path.clone();
+    let settings_clone = settings.clone();
+    tokio::task::spawn_blocking(move || write_settings_file(&config_path, &settings_clone))
+        .await??;
+    let old_settings = std::mem::replace(&mut *state.settings.lock().await, settings.clone());
+    // anything cached for a vault that was removed or moved is stale now
+    let mut vaults = state.vaults.lock().await;
+    for vault in old_settings.vaults {
+        if !settings.vaults.contains(&vault) {
+            vaults.remove(&vault.name);
+        }
+    }
+    drop(vaults);
+    // windows showing a removed vault go back to the active one
+    state
+        .window_vaults
+        .lock()
+        .await
+        .retain(|_, name| settings.vaults.iter().any(|vault| vault.name == *name));
+    restart_watcher(state).await?;
+    Ok(())
+}
+
+/// Makes the vault called `name` the active one, which every window that isn't bound to a
+/// vault of its own shows
+pub async fn switch_vault(state: &AppState, name: String) -> Result<()> {
+    let mut settings = state.settings.lock().await.clone();
+    if !settings.vaults.iter().any(|vault| vault.name == name) {
+        bail!("there is no vault called {name:?}");
+    }
+    settings.active_vault = Some(name);
+    write_settings(state, settings).await
+}
+
+/// Makes the window labeled `window` show the vault called `name` without changing the active
+/// vault, or the active vault again if `name` is None
+pub async fn set_window_vault(
+    state: &AppState,
+    window: Option<&str>,
+    name: Option<String>,
+) -> Result<()> {
+    let Some(window) = window else {
+        if name.is_some() {
+            bail!("only app windows can show a vault other than the active one");
+        }
+        return Ok(());
+    };
+    match name {
+        Some(name) => {
+            // fails if there's no such vault
+            state.for_vault(name.clone()).await?;
+            state
+                .window_vaults
+                .lock()
+                .await
+                .insert(window.to_owned(), name);
+        }
+        None => {
+            state.window_vaults.lock().await.remove(window);
+        }
+    }
     Ok(())
 }
 
@@ -52,8 +210,12 @@ impl Default for Settings {
     fn default() -> Self {
         Self {
             notes_path: None,
+            vaults: Vec::new(),
+            active_vault: None,
             window_states: HashMap::new(),
             minimized_pinned_paths: None,
+            ignored_paths: None,
+            trash_retention_days: None,
         }
     }
 }
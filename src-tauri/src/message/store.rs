@@ -0,0 +1,172 @@
+use std::io::Write;
+use std::path::{Component, Path};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::{Result, bail};
+
+use crate::message::settings::Vault;
+
+pub mod android;
+pub mod desktop;
+pub mod memory;
+
+/// Backend that holds the files of a vault. Paths are `/` separated and relative to the root of
+/// the vault. Operations are blocking, callers are expected to run them off the async runtime.
+pub trait NoteStore: Send + Sync {
+    /// Returns None if the file doesn't exist
+    fn read_bytes(&self, path: &str) -> Result<Option<Vec<u8>>>;
+    /// Creates the file and any missing folders if needed. Must never leave a partially written
+    /// file behind.
+    fn write_bytes(&self, path: &str, contents: &[u8]) -> Result<()>;
+    /// Reads a UTF-8 file, returning None if it doesn't exist
+    fn read(&self, path: &str) -> Result<Option<String>> {
+        Ok(self.read_bytes(path)?.map(String::from_utf8).transpose()?)
+    }
+    fn write(&self, path: &str, contents: &str) -> Result<()> {
+        self.write_bytes(path, contents.as_bytes())
+    }
+    fn remove(&self, path: &str) -> Result<()>;
+    fn exists(&self, path: &str) -> Result<bool>;
+    /// Recursively lists every file, skipping anything [`is_ignored`] by `ignored`
+    fn list(&self, ignored: &[String]) -> Result<Vec<String>>;
+    /// Moves a file, creating any missing folders of `to`
+    fn rename(&self, from: &str, to: &str) -> Result<()>;
+    /// Returns None if the file doesn't exist
+    fn metadata(&self, path: &str) -> Result<Option<FileMetadata>>;
+    /// The folder on disk the store is backed by, if any
+    fn root(&self) -> Option<&Path> {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileMetadata {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Opens the store for a vault, or None if there is no vault to open
+pub type StoreOpener = Arc<dyn Fn(Option<&Vault>) -> Option<Arc<dyn NoteStore>> + Send + Sync>;
+
+/// Store opener for the platform brot is running on: the vault's folder on desktop,
+/// and the folder picked through the storage access framework on android. That folder is the
+/// same for every vault, which is why android settings can't have more than one.
+#[allow(unused)]
+pub fn platform_store_opener(app: &mut tauri::App) -> Result<StoreOpener> {
+    #[cfg(not(target_os = "android"))]
+    {
+        Ok(Arc::new(|vault: Option<&Vault>| {
+            vault.map(|vault| {
+                Arc::new(desktop::DesktopStore::new(vault.path.clone().into()))
+                    as Arc<dyn NoteStore>
+            })
+        }))
+    }
+
+    #[cfg(target_os = "android")]
+    {
+        let store: Arc<dyn NoteStore> = Arc::new(android::AndroidStore::new(app)?);
+        Ok(Arc::new(move |_: Option<&Vault>| Some(store.clone())))
+    }
+}
+
+/// Store opener that always returns the same store, regardless of the vault
+pub fn fixed_store_opener(store: Arc<dyn NoteStore>) -> StoreOpener {
+    Arc::new(move |_: Option<&Vault>| Some(store.clone()))
+}
+
+/// Whether a directory entry should be left out of the vault listing. Dot-directories are
+/// always skipped, and entries in `ignored` match either the entry name or its relative path.
+pub fn is_ignored(relative_path: &str, name: &str, is_dir: bool, ignored: &[String]) -> bool {
+    (is_dir && name.starts_with('.'))
+        || ignored
+            .iter()
+            .any(|pattern| pattern == name || pattern.trim_matches('/') == relative_path)
+}
+
+/// Whether a file at `relative_path` lives somewhere [`NoteStore::list`] would skip
+pub fn is_path_ignored(relative_path: &str, ignored: &[String]) -> bool {
+    let mut prefix = String::new();
+    let mut parts = relative_path.split('/').peekable();
+    while let Some(name) = parts.next() {
+        prefix = join_relative(&prefix, name);
+        if is_ignored(&prefix, name, parts.peek().is_some(), ignored) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Normalizes a path relative to the root of a vault into its `/` separated parts, leaving out
+/// `.` parts. Empty and absolute paths and paths with `..` parts are rejected, since they could
+/// reach files outside the vault.
+pub fn vault_path(path: &str) -> Result<String> {
+    let mut parts = Vec::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy()),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                bail!("{path:?} is not a path inside the vault")
+            }
+        }
+    }
+    if parts.is_empty() {
+        bail!("{path:?} is not a path inside the vault");
+    }
+    Ok(parts.join("/"))
+}
+
+pub fn join_relative(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{prefix}/{name}")
+    }
+}
+
+/// Path of the temporary file a write to `path` goes through
+pub fn tmp_path(path: &str) -> String {
+    format!("{path}.tmp")
+}
+
+/// Path of the last good version of a json file at `path`
+pub fn backup_path(path: &str) -> String {
+    format!("{path}.bak")
+}
+
+/// Writes `contents` to a temporary file next to `path`, syncs it and renames it over `path`,
+/// so a crash never leaves a partially written file behind
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent)?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "no file name"))?;
+    let tmp = path.with_file_name(tmp_path(&file_name.to_string_lossy()));
+    {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    std::fs::rename(&tmp, path)?;
+    // make the rename itself durable
+    #[cfg(unix)]
+    std::fs::File::open(parent)?.sync_all()?;
+    Ok(())
+}
+
+/// Like [`write_atomic`], but first copies the current contents of `path` to its backup if
+/// they are still valid json
+pub fn write_atomic_with_backup(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Ok(current) = std::fs::read(path)
+        && serde_json::from_slice::<serde_json::Value>(&current).is_ok()
+    {
+        let backup = path.with_file_name(backup_path(
+            &path.file_name().unwrap_or_default().to_string_lossy(),
+        ));
+        write_atomic(&backup, &current)?;
+    }
+    write_atomic(path, contents)
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use anyhow::anyhow;
+use tauri::{App, AppHandle};
+use tauri_plugin_android_fs::Entry;
+use tauri_plugin_android_fs::{AndroidFsExt, FileUri, PersistableAccessMode, PrivateDir};
+
+use crate::message::store::{FileMetadata, NoteStore, is_ignored, join_relative, tmp_path};
+
+#[allow(unused)]
+fn downloads_subfolder_uri(app: &mut App) -> Result<FileUri> {
+    let api = app.android_fs();
+
+    let storage_key = format!("subfolder_uri");
+
+    let stored_uri = api
+        .private_storage()
+        .read_to_string(PrivateDir::Data, &storage_key)
+        .ok()
+        .and_then(|s| FileUri::from_str(&s).ok());
+
+    if let Some(uri) = stored_uri {
+        if api.check_persisted_uri_permission(&uri, PersistableAccessMode::ReadAndWrite)? {
+            return Ok(uri);
+        }
+    }
+
+    let selected_uri = api.show_manage_dir_dialog(None)?;
+
+    let Some(uri) = selected_uri else {
+        return Err(anyhow!("User cancelled folder selection"));
+    };
+
+    api.take_persistable_uri_permission(&uri)?;
+
+    api.private_storage()
+        .write(PrivateDir::Data, &storage_key, uri.to_string()?.as_bytes())?;
+
+    Ok(uri)
+}
+
+/// Store backed by a folder the user picked through the storage access framework
+pub struct AndroidStore {
+    app: AppHandle,
+    uri: FileUri,
+}
+
+impl AndroidStore {
+    #[allow(unused)]
+    pub fn new(app: &mut App) -> Result<Self> {
+        Ok(Self {
+            uri: downloads_subfolder_uri(app)?,
+            app: app.handle().clone(),
+        })
+    }
+
+    fn write_file(&self, path: &str, contents: &[u8]) -> Result<()> {
+        let api = self.app.android_fs();
+        if !self.exists(path)? {
+            api.create_file(&self.uri, path, None)?;
+        }
+        let file_uri = api.resolve_uri(&self.uri, path)?;
+        api.write(&file_uri, contents)?;
+        Ok(())
+    }
+}
+
+impl NoteStore for AndroidStore {
+    fn read_bytes(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        if !self.exists(path)? {
+            return Ok(None);
+        }
+        let api = self.app.android_fs();
+        match api.resolve_uri(&self.uri, path) {
+            Ok(file_uri) => Ok(Some(api.read(&file_uri)?)),
+            Err(tauri_plugin_android_fs::Error::Io(e))
+                if e.kind() == std::io::ErrorKind::NotFound =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_bytes(&self, path: &str, contents: &[u8]) -> Result<()> {
+        // the storage access framework can't rename over a file, so a complete copy is
+        // written first that survives if overwriting the real file gets interrupted
+        let tmp_path = tmp_path(path);
+        self.write_file(&tmp_path, contents)?;
+        self.write_file(path, contents)?;
+        self.remove(&tmp_path)
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        let api = self.app.android_fs();
+        let file_uri = api.resolve_uri(&self.uri, path)?;
+        api.remove_file(&file_uri)?;
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        let api = self.app.android_fs();
+        match api.resolve_uri(&self.uri, path) {
+            Ok(uri) => match api.get_mime_type(&uri) {
+                Ok(Some(_)) => Ok(true),
+                Ok(None) => Ok(false),
+                Err(tauri_plugin_android_fs::Error::Io(e))
+                    if e.kind() == std::io::ErrorKind::NotFound =>
+                {
+                    Ok(false)
+                }
+                // TODO find better solution for this
+                Err(tauri_plugin_android_fs::Error::PluginInvoke(message))
+                    if message.contains("java.io.FileNotFoundException") =>
+                {
+                    Ok(false)
+                }
+
+                Err(err) => Err(err.into()),
+            },
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn list(&self, ignored: &[String]) -> Result<Vec<String>> {
+        let api = self.app.android_fs();
+        let mut paths = Vec::new();
+        let mut dirs = vec![(self.uri.clone(), String::new())];
+        while let Some((dir, prefix)) = dirs.pop() {
+            for entry in api.read_dir(&dir)? {
+                match entry {
+                    Entry::File { name, .. } => {
+                        let relative_path = join_relative(&prefix, &name);
+                        if !is_ignored(&relative_path, &name, false, ignored) {
+                            paths.push(relative_path);
+                        }
+                    }
+                    Entry::Dir { name, uri, .. } => {
+                        let relative_path = join_relative(&prefix, &name);
+                        if !is_ignored(&relative_path, &name, true, ignored) {
+                            dirs.push((uri, relative_path));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        // moving between folders isn't supported by every document provider, so copy instead
+        let Some(contents) = self.read_bytes(from)? else {
+            return Err(anyhow!("{from:?} does not exist"));
+        };
+        self.write_bytes(to, &contents)?;
+        self.remove(from)
+    }
+
+    fn metadata(&self, path: &str) -> Result<Option<FileMetadata>> {
+        Ok(self.read_bytes(path)?.map(|contents| FileMetadata {
+            len: contents.len() as u64,
+            modified: None,
+        }))
+    }
+}
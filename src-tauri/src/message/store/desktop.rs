@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::message::store::{
+    FileMetadata, NoteStore, is_ignored, join_relative, vault_path, write_atomic,
+};
+
+/// Store backed by a plain folder on disk
+pub struct DesktopStore {
+    root: PathBuf,
+}
+
+impl DesktopStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Where the file at `path` is on disk, which is always inside the root
+    fn path(&self, path: &str) -> Result<PathBuf> {
+        Ok(self.root.join(vault_path(path)?))
+    }
+}
+
+impl NoteStore for DesktopStore {
+    fn read_bytes(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        match std::fs::read(self.path(path)?) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_bytes(&self, path: &str, contents: &[u8]) -> Result<()> {
+        write_atomic(&self.path(path)?, contents)?;
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        std::fs::remove_file(self.path(path)?)?;
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        Ok(std::fs::exists(self.path(path)?)?)
+    }
+
+    fn list(&self, ignored: &[String]) -> Result<Vec<String>> {
+        let mut paths = Vec::new();
+        let mut dirs = vec![(self.root.clone(), String::new())];
+        while let Some((dir, prefix)) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let name = entry
+                    .file_name()
+                    .into_string()
+                    .expect("invalid entry string");
+                let relative_path = join_relative(&prefix, &name);
+                let is_dir = entry.file_type()?.is_dir();
+                if is_ignored(&relative_path, &name, is_dir, ignored) {
+                    continue;
+                }
+                if is_dir {
+                    dirs.push((entry.path(), relative_path));
+                } else {
+                    paths.push(relative_path);
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let to = self.path(to)?;
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(self.path(from)?, to)?;
+        Ok(())
+    }
+
+    fn metadata(&self, path: &str) -> Result<Option<FileMetadata>> {
+        match std::fs::metadata(self.path(path)?) {
+            Ok(metadata) => Ok(Some(FileMetadata {
+                len: metadata.len(),
+                modified: metadata.modified().ok(),
+            })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn root(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_stay_inside_the_root() {
+        let store = DesktopStore::new(PathBuf::from("/vault"));
+        assert_eq!(
+            store.path("./notes/a.md").unwrap(),
+            PathBuf::from("/vault/notes/a.md")
+        );
+        assert!(store.path("../secret.md").is_err());
+        assert!(store.path("notes/../../secret.md").is_err());
+        assert!(store.path("/etc/passwd").is_err());
+        assert!(store.path("").is_err());
+        assert!(store.read("../secret.md").is_err());
+    }
+}
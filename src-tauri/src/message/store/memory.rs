@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::{Result, anyhow};
+
+use crate::message::store::{FileMetadata, NoteStore, is_path_ignored, vault_path};
+
+/// Store that only lives in memory, for running brot without touching the disk
+#[derive(Default)]
+pub struct MemoryStore {
+    files: Mutex<BTreeMap<String, (Vec<u8>, SystemTime)>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a store that already contains the given `(path, contents)` files
+    pub fn with_files<'a>(files: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let now = SystemTime::now();
+        Self {
+            files: Mutex::new(
+                files
+                    .into_iter()
+                    .map(|(path, contents)| {
+                        let path = vault_path(path).expect("invalid path in memory store");
+                        (path, (contents.as_bytes().to_vec(), now))
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl NoteStore for MemoryStore {
+    fn read_bytes(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let path = vault_path(path)?;
+        let files = self.files.lock().unwrap();
+        Ok(files.get(&path).map(|(contents, _)| contents.clone()))
+    }
+
+    fn write_bytes(&self, path: &str, contents: &[u8]) -> Result<()> {
+        let path = vault_path(path)?;
+        let mut files = self.files.lock().unwrap();
+        files.insert(path, (contents.to_vec(), SystemTime::now()));
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> Result<()> {
+        let path = vault_path(path)?;
+        let mut files = self.files.lock().unwrap();
+        files
+            .remove(&path)
+            .map(|_| ())
+            .ok_or_else(|| anyhow!("{path:?} does not exist"))
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        let path = vault_path(path)?;
+        Ok(self.files.lock().unwrap().contains_key(&path))
+    }
+
+    fn list(&self, ignored: &[String]) -> Result<Vec<String>> {
+        let files = self.files.lock().unwrap();
+        Ok(files
+            .keys()
+            .filter(|path| !is_path_ignored(path, ignored))
+            .cloned()
+            .collect())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let (from, to) = (vault_path(from)?, vault_path(to)?);
+        let mut files = self.files.lock().unwrap();
+        let file = files
+            .remove(&from)
+            .ok_or_else(|| anyhow!("{from:?} does not exist"))?;
+        files.insert(to, file);
+        Ok(())
+    }
+
+    fn metadata(&self, path: &str) -> Result<Option<FileMetadata>> {
+        let path = vault_path(path)?;
+        let files = self.files.lock().unwrap();
+        Ok(files.get(&path).map(|(contents, modified)| FileMetadata {
+            len: contents.len() as u64,
+            modified: Some(*modified),
+        }))
+    }
+}
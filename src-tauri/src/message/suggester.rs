@@ -4,7 +4,10 @@ use ts_rs::TS;
 
 use crate::{
     message::{
-        meta::read_meta, palette_action::Matched, searcher::SearcherId, tag::construct_all_tags,
+        meta::read_meta,
+        palette_action::Matched,
+        searcher::{SearchField, SearcherId, evict_idle_searchers},
+        tag::{TagSource, construct_all_tags},
     },
     state::AppState,
 };
@@ -15,25 +18,67 @@ pub enum SuggesterSource {
     Tag,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
 #[ts(export)]
 #[serde(rename_all = "camelCase")]
 pub struct Suggestion {
     pub value: String,
+    /// where a suggested tag is written, so tags from note contents can be told apart from
+    /// file name tags
+    pub sources: Vec<TagSource>,
+    /// the alias that was matched, when the suggestion is the tag an alias stands for
+    pub alias: Option<String>,
 }
 
-pub async fn create_suggester(state: &AppState, _source: SuggesterSource) -> Result<SearcherId> {
+/// What tag suggestions are matched by, the tag itself or the alias it is suggested for
+pub fn suggestion_search_fields() -> Vec<SearchField<Suggestion>> {
+    vec![
+        SearchField {
+            name: "value",
+            scope: "v",
+            weight: 1.0,
+            unscoped: true,
+            get: |suggestion| suggestion.value.clone(),
+        },
+        SearchField {
+            name: "alias",
+            scope: "a",
+            weight: 1.0,
+            unscoped: true,
+            get: |suggestion| suggestion.alias.clone().unwrap_or_default(),
+        },
+    ]
+}
+
+pub async fn create_suggester(
+    state: &AppState,
+    _source: SuggesterSource,
+    window: Option<&str>,
+) -> Result<SearcherId> {
     let suggestions = read_meta(state, |holder| {
+        let aliases = holder
+            .tag_resolver()
+            .aliases()
+            .iter()
+            .map(|(alias, canonical)| Suggestion {
+                value: format!("-{}", canonical.join("--")),
+                sources: Vec::new(),
+                alias: Some(format!("-{}", alias.join("--"))),
+            });
         construct_all_tags(&holder.tags())
             .into_iter()
-            .map(|s| Suggestion {
-                value: s.to_string(),
+            .map(|(value, sources)| Suggestion {
+                value,
+                sources,
+                alias: None,
             })
+            .chain(aliases)
             .collect::<Vec<_>>()
     })
     .await?;
-    let mut suggesters = state.suggesters.write().await;
-    Ok(suggesters.new_searcher(&suggestions))
+    let vault = state.vault().await;
+    let mut suggesters = vault.suggesters.write().await;
+    Ok(suggesters.new_searcher(&suggestions, None, window))
 }
 
 pub async fn search_suggester(
@@ -41,11 +86,15 @@ pub async fn search_suggester(
     id: SearcherId,
     search: String,
 ) -> Result<Option<Vec<Matched<Suggestion>>>> {
-    let suggesters = state.suggesters.read().await;
-    Ok(suggesters.search(id, &search, 0..5).await?)
+    evict_idle_searchers(state).await;
+    let vault = state.vault().await;
+    let suggesters = vault.suggesters.read().await;
+    let results = suggesters.search(id, &search, 0..5).await?;
+    Ok(results.map(|results| results.matches))
 }
 
 pub async fn delete_suggester(state: &AppState, id: SearcherId) {
-    let mut suggesters = state.suggesters.write().await;
+    let vault = state.vault().await;
+    let mut suggesters = vault.suggesters.write().await;
     suggesters.delete_searcher(id);
 }
@@ -1,12 +1,57 @@
-use std::sync::LazyLock;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::LazyLock,
+};
 
+use anyhow::{Result, bail};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 
-use crate::message::{meta::Meta, title::path_to_title};
+use crate::{
+    message::{
+        meta::{Meta, TagConfig, read_meta, write_meta},
+        property::{Properties, list_property, note_body},
+        tag_alias::TagResolver,
+        title::{path_file_name, path_to_title},
+    },
+    state::AppState,
+};
 
+/// Where a tag is written in a note
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub enum TagSource {
+    /// a `-tag` unit in the file name
+    Path,
+    /// a `#tag` in the body
+    Body,
+    /// the `tags` list of the front matter
+    FrontMatter,
+}
+
+/// A tag written in the content of a note rather than its file name
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentTag {
+    pub parts: Vec<String>,
+    pub source: TagSource,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
 pub struct TagNode {
     pub name: String,
     pub children: Vec<TagNode>,
+    /// where the tag or any of its subtags are written
+    pub sources: BTreeSet<TagSource>,
+    /// how many notes have the tag or any of its subtags
+    pub note_count: u32,
+    /// index of the last note counted, so a note with several subtags is only counted once
+    #[serde(skip)]
+    #[ts(skip)]
+    counted_note: Option<usize>,
 }
 
 impl TagNode {
@@ -14,35 +59,106 @@ impl TagNode {
         Self {
             name,
             children: Vec::new(),
+            sources: BTreeSet::new(),
+            note_count: 0,
+            counted_note: None,
         }
     }
 }
 
 /// Turn a path like "-hello--there_my_name_-is_-joe--dan-iel--john.md" -> [[hello, there], [is], [joe, dan-iel, john]]
-fn extract_tag_units(path: &str) -> Vec<Vec<String>> {
-    static TWO_OR_MORE_DASHES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"-{2,}").unwrap());
-    let re = &*TWO_OR_MORE_DASHES;
-    path_to_title(path)
+///
+/// Only the file name is considered, so folders never contribute tags.
+pub fn extract_tag_units(path: &str) -> Vec<Vec<String>> {
+    path_to_title(path_file_name(path))
         .split_whitespace()
         .filter(|s| s.starts_with('-'))
-        .map(|s| s.trim_start_matches('-'))
-        .map(|unit| re.split(unit).map(|x| x.to_string()).collect())
+        .map(split_tag_unit)
+        .collect()
+}
+
+/// Turn a tag unit like "-joe--dan-iel--john" -> [joe, dan-iel, john]
+pub fn split_tag_unit(unit: &str) -> Vec<String> {
+    static TWO_OR_MORE_DASHES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"-{2,}").unwrap());
+    TWO_OR_MORE_DASHES
+        .split(unit.trim_start_matches('-'))
+        .map(|x| x.to_string())
+        .collect()
+}
+
+/// Turn a tag like "#project/alpha" -> [project, alpha]
+pub fn split_content_tag(tag: &str) -> Vec<String> {
+    tag.trim()
+        .trim_start_matches('#')
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_string())
         .collect()
 }
 
-pub fn tags_from_meta(meta: &Meta) -> Vec<TagNode> {
+/// Finds the `#tags` in the body of a note and the tags listed in its front matter
+pub fn extract_content_tags(content: &str, properties: Option<&Properties>) -> Vec<ContentTag> {
+    // a tag starts a word and needs more than digits, so `#1` or a `# heading` isn't one
+    static INLINE_TAG: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?:^|\s)#([\p{L}\p{N}_/-]*[\p{L}_-][\p{L}\p{N}_/-]*)").unwrap()
+    });
+    let mut tags = Vec::new();
+
+    for tag in list_property(properties, "tags") {
+        tags.push(ContentTag {
+            parts: split_content_tag(tag),
+            source: TagSource::FrontMatter,
+        });
+    }
+
+    let mut in_code_block = false;
+    for line in note_body(content).lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block {
+            continue;
+        }
+        for captures in INLINE_TAG.captures_iter(line) {
+            tags.push(ContentTag {
+                parts: split_content_tag(&captures[1]),
+                source: TagSource::Body,
+            });
+        }
+    }
+
+    tags.retain(|tag| !tag.parts.is_empty());
+    tags.dedup();
+    tags
+}
+
+pub fn tags_from_meta(
+    meta: &Meta,
+    content_tags: &HashMap<String, Vec<ContentTag>>,
+    resolver: &TagResolver,
+) -> Vec<TagNode> {
     let mut nodes: Vec<TagNode> = Vec::new();
 
-    for path in meta.notes.keys() {
+    for (note, path) in meta.notes.keys().enumerate() {
         for tag_parts in extract_tag_units(path) {
-            insert_tag_parts(&tag_parts, &mut nodes);
+            let tag_parts = resolver.resolve(&tag_parts);
+            insert_tag_parts(&tag_parts, TagSource::Path, note, &mut nodes);
+        }
+        for tag in content_tags.get(path).into_iter().flatten() {
+            let tag_parts = resolver.resolve(&tag.parts);
+            insert_tag_parts(&tag_parts, tag.source, note, &mut nodes);
         }
     }
 
     nodes
 }
 
-fn insert_tag_parts(tag_parts: &[String], nodes: &mut Vec<TagNode>) {
+fn insert_tag_parts(
+    tag_parts: &[String],
+    source: TagSource,
+    note: usize,
+    nodes: &mut Vec<TagNode>,
+) {
     match tag_parts {
         [] => {}
         [head, tail @ ..] => {
@@ -55,22 +171,157 @@ fn insert_tag_parts(tag_parts: &[String], nodes: &mut Vec<TagNode>) {
                 });
 
             let child_tag_node = &mut nodes[index];
-            insert_tag_parts(tail, &mut child_tag_node.children);
+            child_tag_node.sources.insert(source);
+            if child_tag_node.counted_note != Some(note) {
+                child_tag_node.counted_note = Some(note);
+                child_tag_node.note_count += 1;
+            }
+            insert_tag_parts(tail, source, note, &mut child_tag_node.children);
         }
     }
 }
 
-pub fn construct_all_tags(nodes: &[TagNode]) -> Vec<String> {
+/// Every tag in the tree the way it is written in a file name, like "-project--alpha", along
+/// with where it is written
+pub fn construct_all_tags(nodes: &[TagNode]) -> Vec<(String, Vec<TagSource>)> {
     nodes
         .iter()
         .flat_map(|node| {
-            std::iter::once(format!("-{}", node.name))
+            let sources = node.sources.iter().copied().collect();
+            std::iter::once((format!("-{}", node.name), sources))
                 .chain(
                     construct_all_tags(&node.children)
-                        .iter()
-                        .map(|child| format!("-{}-{}", node.name, child)),
+                        .into_iter()
+                        .map(|(child, sources)| (format!("-{}-{}", node.name, child), sources)),
                 )
                 .collect::<Vec<_>>()
         })
         .collect()
 }
+
+/// Sorts a tag tree by the sort order of the tags' configs, then by name. Tags without a sort
+/// order go after the ones that have one.
+pub fn sort_tag_tree(
+    nodes: &mut [TagNode],
+    tag_configs: &HashMap<String, TagConfig>,
+    prefix: &str,
+) {
+    let key = |node: &TagNode| format!("{prefix}{}", node.name);
+    let sort_order = |node: &TagNode| {
+        tag_configs
+            .get(&key(node))
+            .and_then(|config| config.sort_order)
+    };
+    nodes.sort_by(|a, b| {
+        let order = match (sort_order(a), sort_order(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        };
+        order.then_with(|| a.name.cmp(&b.name))
+    });
+    for node in nodes {
+        let prefix = format!("{}--", key(node));
+        sort_tag_tree(&mut node.children, tag_configs, &prefix);
+    }
+}
+
+/// Turns a tag like "-project--alpha" into its tag config key, "project--alpha"
+pub fn tag_config_key(tag: &str) -> Result<String> {
+    let parts = split_tag_unit(tag.trim());
+    if parts.iter().any(String::is_empty) {
+        bail!("invalid tag {tag:?}");
+    }
+    Ok(parts.join("--"))
+}
+
+/// The tag tree of the vault, sorted for showing it
+pub async fn get_tag_tree(state: &AppState) -> Result<Vec<TagNode>> {
+    read_meta(state, |holder| {
+        let mut nodes = holder.tags().to_vec();
+        sort_tag_tree(&mut nodes, &holder.meta().tag_configs, "");
+        nodes
+    })
+    .await
+}
+
+/// Replaces the config of a tag, keeping any fields a newer brot stored in it
+pub async fn set_tag_config(state: &AppState, tag: &str, mut config: TagConfig) -> Result<()> {
+    let key = tag_config_key(tag)?;
+    write_meta(state, |holder| {
+        holder.update_meta(|meta| {
+            if let Some(existing) = meta.tag_configs.get(&key) {
+                config.extra = existing.extra.clone();
+            }
+            meta.tag_configs.insert(key.clone(), config.clone());
+        })
+    })
+    .await
+}
+
+pub async fn clear_tag_config(state: &AppState, tag: &str) -> Result<()> {
+    let key = tag_config_key(tag)?;
+    write_meta(state, |holder| {
+        holder.update_meta(|meta| meta.tag_configs.remove(&key));
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_come_from_the_file_name_only() {
+        assert_eq!(
+            extract_tag_units("-archive/plan_-project--alpha_-draft.md"),
+            [vec!["project", "alpha"], vec!["draft"]]
+        );
+    }
+
+    #[test]
+    fn content_tags_skip_headings_numbers_and_code() {
+        let content = "# heading #1\n#idea and #project/alpha\n```\n#code\n```\n";
+        let parts: Vec<Vec<String>> = extract_content_tags(content, None)
+            .into_iter()
+            .map(|tag| tag.parts)
+            .collect();
+        assert_eq!(parts, [vec!["idea"], vec!["project", "alpha"]]);
+    }
+
+    #[tokio::test]
+    async fn the_tag_tree_counts_each_note_once() {
+        let state = AppState::in_memory(&[
+            ("a_-project--alpha.md", "#project/beta"),
+            ("b_-project.md", ""),
+            ("c.md", "#idea"),
+        ]);
+        set_tag_config(
+            &state,
+            "-project",
+            TagConfig {
+                sort_order: Some(0),
+                ..TagConfig::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let tree = get_tag_tree(&state).await.unwrap();
+        let names: Vec<&str> = tree.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, ["project", "idea"]);
+        assert_eq!(tree[0].note_count, 2);
+        assert_eq!(
+            tree[0].sources,
+            BTreeSet::from([TagSource::Path, TagSource::Body])
+        );
+        let children: Vec<&str> = tree[0]
+            .children
+            .iter()
+            .map(|node| node.name.as_str())
+            .collect();
+        assert_eq!(children, ["alpha", "beta"]);
+        assert_eq!(tree[1].note_count, 1);
+    }
+}
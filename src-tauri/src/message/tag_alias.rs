@@ -0,0 +1,264 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    message::{
+        meta::{Meta, read_meta, write_meta},
+        tag::{construct_all_tags, split_tag_unit},
+    },
+    state::AppState,
+};
+
+/// How tag names are normalized before they are compared and put in the tag tree
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct TagNormalization {
+    /// fold case, so `-ML` and `-ml` are the same tag
+    #[serde(default)]
+    pub case: bool,
+    /// apply unicode compatibility normalization (NFKC), so `-ﬁle` and `-file` are the same tag
+    #[serde(default)]
+    pub unicode: bool,
+}
+
+impl TagNormalization {
+    pub fn normalize(&self, part: &str) -> String {
+        let part: String = if self.unicode {
+            part.nfkc().collect()
+        } else {
+            part.to_owned()
+        };
+        if self.case { part.to_lowercase() } else { part }
+    }
+}
+
+/// Turns tags into their canonical form, by normalizing them and then replacing aliases with
+/// the tag whose config lists them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TagResolver {
+    normalization: TagNormalization,
+    /// alias parts and the parts of the tag they stand for, longest alias first
+    aliases: Vec<(Vec<String>, Vec<String>)>,
+}
+
+impl TagResolver {
+    pub fn new(meta: &Meta) -> Self {
+        let normalization = meta.tag_normalization;
+        let normalize = |tag: &str| -> Vec<String> {
+            split_tag_unit(tag.trim())
+                .iter()
+                .map(|part| normalization.normalize(part))
+                .collect()
+        };
+        let mut aliases: Vec<_> = meta
+            .tag_configs
+            .iter()
+            .flat_map(|(key, config)| {
+                let canonical = normalize(key);
+                config
+                    .aliases
+                    .iter()
+                    .map(move |alias| (normalize(alias), canonical.clone()))
+            })
+            .filter(|(alias, canonical)| alias != canonical && !alias.iter().any(String::is_empty))
+            .collect();
+        aliases.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        Self {
+            normalization,
+            aliases,
+        }
+    }
+
+    /// Follows aliases of aliases too, stopping once a cycle comes back to a tag already seen
+    pub fn resolve(&self, parts: &[String]) -> Vec<String> {
+        let mut parts: Vec<String> = parts
+            .iter()
+            .map(|part| self.normalization.normalize(part))
+            .collect();
+        let mut seen = Vec::new();
+        while let Some((alias, canonical)) = self
+            .aliases
+            .iter()
+            .find(|(alias, _)| parts.starts_with(alias))
+        {
+            if seen.contains(&parts) {
+                break;
+            }
+            seen.push(parts.clone());
+            parts.splice(..alias.len(), canonical.iter().cloned());
+        }
+        parts
+    }
+
+    /// Every alias along with the tag it stands for
+    pub fn aliases(&self) -> &[(Vec<String>, Vec<String>)] {
+        &self.aliases
+    }
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq)]
+#[ts(export)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DuplicateReason {
+    /// the tags are the same when case, unicode compatibility characters and dashes are ignored
+    Folded,
+    /// the tags are a few single character edits apart
+    #[serde(rename_all = "camelCase")]
+    EditDistance {
+        #[ts(type = "number")]
+        distance: usize,
+    },
+}
+
+/// Two tags that are probably meant to be the same one
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct TagDuplicate {
+    /// the tags the way they are written in file names, like "-machine-learning"
+    pub first: String,
+    pub second: String,
+    pub reason: DuplicateReason,
+}
+
+/// Folds the parts of a tag for comparing it with others
+fn fold_tag(tag: &str) -> Vec<String> {
+    let folding = TagNormalization {
+        case: true,
+        unicode: true,
+    };
+    split_tag_unit(tag)
+        .iter()
+        .map(|part| folding.normalize(part).replace('-', ""))
+        .collect()
+}
+
+/// How many edits apart two folded tags of this length may be to count as duplicates. Short
+/// tags like `-ml` and `-ai` are too easily a few edits apart to compare them at all.
+fn max_distance(len: usize) -> usize {
+    match len {
+        0..5 => 0,
+        5..10 => 1,
+        _ => 2,
+    }
+}
+
+/// Finds pairs of tags in the tag tree that are probably duplicates of each other. Only
+/// siblings are compared by edit distance, since subtags like `-release--v1` and `-release--v2`
+/// are meant to differ by little. Tags that are already folded together through aliases or
+/// normalization aren't reported.
+pub async fn find_duplicate_tags(state: &AppState) -> Result<Vec<TagDuplicate>> {
+    let tags: Vec<String> = read_meta(state, |holder| {
+        construct_all_tags(holder.tags())
+            .into_iter()
+            .map(|(tag, _)| tag)
+            .collect()
+    })
+    .await?;
+    let mut folded: Vec<(Vec<String>, String)> =
+        tags.into_iter().map(|tag| (fold_tag(&tag), tag)).collect();
+    folded.sort();
+
+    let mut duplicates = Vec::new();
+    for (i, (a_folded, a)) in folded.iter().enumerate() {
+        for (b_folded, b) in &folded[i + 1..] {
+            let reason = if a_folded == b_folded {
+                DuplicateReason::Folded
+            } else if let Some((a_name, a_parent)) = a_folded.split_last()
+                && let Some((b_name, b_parent)) = b_folded.split_last()
+                && a_parent == b_parent
+            {
+                let len = a_name.chars().count().min(b_name.chars().count());
+                let distance = strsim::levenshtein(a_name, b_name);
+                if distance > max_distance(len) {
+                    continue;
+                }
+                DuplicateReason::EditDistance { distance }
+            } else {
+                continue;
+            };
+            duplicates.push(TagDuplicate {
+                first: a.clone(),
+                second: b.clone(),
+                reason,
+            });
+        }
+    }
+    Ok(duplicates)
+}
+
+pub async fn set_tag_normalization(
+    state: &AppState,
+    normalization: TagNormalization,
+) -> Result<()> {
+    write_meta(state, |holder| {
+        holder.update_meta(|meta| meta.tag_normalization = normalization)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{meta::TagConfig, tag::get_tag_tree};
+
+    fn config(aliases: &[&str]) -> TagConfig {
+        TagConfig {
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            ..TagConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn spellings_and_aliases_fold_into_one_tag() {
+        let mut meta = Meta {
+            tag_normalization: TagNormalization {
+                case: true,
+                unicode: false,
+            },
+            ..Meta::default()
+        };
+        meta.tag_configs
+            .insert("-ml".to_owned(), config(&["-machine-learning"]));
+        let state = AppState::in_memory_with_meta(
+            &[
+                ("a_-ML.md", ""),
+                ("b_-ml.md", ""),
+                ("c_-machine-learning--vision.md", ""),
+            ],
+            &meta,
+        );
+
+        let tree = get_tag_tree(&state).await.unwrap();
+        let names: Vec<&str> = tree.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, ["ml"]);
+        assert_eq!(tree[0].note_count, 3);
+        assert_eq!(tree[0].children[0].name, "vision");
+    }
+
+    #[test]
+    fn alias_chains_are_followed() {
+        let mut meta = Meta::default();
+        meta.tag_configs
+            .insert("-machine-learning".to_owned(), config(&["-ml"]));
+        meta.tag_configs.insert("-ml".to_owned(), config(&["-ai"]));
+        let resolver = TagResolver::new(&meta);
+        assert_eq!(
+            resolver.resolve(&split_tag_unit("-ai")),
+            ["machine-learning"]
+        );
+    }
+
+    #[test]
+    fn alias_cycles_stop() {
+        let mut meta = Meta::default();
+        meta.tag_configs.insert("-a".to_owned(), config(&["-b"]));
+        meta.tag_configs.insert("-b".to_owned(), config(&["-a"]));
+        let resolver = TagResolver::new(&meta);
+        assert_eq!(resolver.resolve(&split_tag_unit("-a")), ["a"]);
+        assert_eq!(resolver.resolve(&split_tag_unit("-b")), ["b"]);
+    }
+}
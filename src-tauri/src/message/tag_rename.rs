@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    message::{
+        folder_manager::file_exists,
+        meta::{read_meta, write_meta},
+        note::update_path,
+        tag::{extract_tag_units, split_tag_unit},
+        title::{path_to_title, sanitize_filename, title_to_path},
+    },
+    state::AppState,
+};
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug, Default)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct TagRewrite {
+    /// notes that were renamed, or would be on a dry run
+    pub notes: Vec<Rename>,
+    /// notes that are left alone because another note already has their new path
+    pub conflicts: Vec<Rename>,
+    /// tag config keys that move along with the tag
+    pub tag_configs: Vec<Rename>,
+    /// notes that have the tag as a `#tag` or in their front matter, which is left alone
+    pub content_tagged: Vec<String>,
+}
+
+/// Parses a tag like "project--alpha" into its parts, the way it appears in tag config keys
+fn parse_tag(tag: &str) -> Result<Vec<String>> {
+    let parts = split_tag_unit(tag.trim());
+    for part in &parts {
+        // underscores would turn into spaces and split the tag when it's in a title
+        if part.is_empty() || part.contains('_') || sanitize_filename(part) != *part {
+            bail!("invalid tag {tag:?}");
+        }
+    }
+    Ok(parts)
+}
+
+/// Whether any of `tags` is `parts` or one of its subtags
+fn has_tag<'a>(mut tags: impl Iterator<Item = &'a [String]>, parts: &[String]) -> bool {
+    tags.any(|tag| tag.starts_with(parts))
+}
+
+/// Replaces the `from` prefix of every tag unit in a note's file name with `to`. Returns None
+/// if the note doesn't have the tag.
+fn rewrite_path(path: &str, from: &[String], to: &[String]) -> Option<String> {
+    let (folder, file_name) = match path.rsplit_once('/') {
+        Some((folder, file_name)) => (Some(folder), file_name),
+        None => (None, path),
+    };
+    let stem = file_name.strip_suffix(".md")?;
+    let is_unit = |word: &&str| word.starts_with('-');
+    let mut units: HashSet<Vec<String>> = stem
+        .split('_')
+        .filter(is_unit)
+        .map(split_tag_unit)
+        .filter(|parts| !parts.starts_with(from))
+        .collect();
+
+    let mut changed = false;
+    let mut words = Vec::new();
+    for word in stem.split('_') {
+        let mut parts = split_tag_unit(word);
+        if !is_unit(&word) || !parts.starts_with(from) {
+            words.push(word.to_owned());
+            continue;
+        }
+        changed = true;
+        parts.splice(..from.len(), to.iter().cloned());
+        // merging can give a note the same tag twice
+        if units.insert(parts.clone()) {
+            words.push(format!("-{}", parts.join("--")));
+        }
+    }
+    if !changed {
+        return None;
+    }
+    let file_name = format!("{}.md", words.join("_"));
+    let path = match folder {
+        Some(folder) => format!("{folder}/{file_name}"),
+        None => file_name,
+    };
+    // the note is renamed through its title, which normalizes the path
+    Some(title_to_path(&path_to_title(&path)))
+}
+
+async fn rewrite_tag(
+    state: &AppState,
+    from: &str,
+    to: &str,
+    merge: bool,
+    dry_run: bool,
+) -> Result<TagRewrite> {
+    let from_parts = parse_tag(from)?;
+    let to_parts = parse_tag(to)?;
+    if from_parts == to_parts {
+        bail!("the tag is already called {to:?}");
+    }
+    let (mut paths, mut config_keys, mut content_tagged, to_in_content) =
+        read_meta(state, |holder| {
+            let content_tagged = |parts: &[String]| {
+                holder
+                    .meta()
+                    .notes
+                    .keys()
+                    .filter(|path| {
+                        let tags = holder.content_tags(path).iter().map(|tag| &tag.parts[..]);
+                        has_tag(tags, parts)
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            };
+            (
+                holder.meta().notes.keys().cloned().collect::<Vec<_>>(),
+                holder
+                    .meta()
+                    .tag_configs
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>(),
+                content_tagged(&from_parts),
+                !content_tagged(&to_parts).is_empty(),
+            )
+        })
+        .await?;
+    paths.sort();
+    config_keys.sort();
+    content_tagged.sort();
+
+    let in_file_names = |parts: &[String]| {
+        paths
+            .iter()
+            .any(|path| has_tag(extract_tag_units(path).iter().map(Vec::as_slice), parts))
+    };
+    if !in_file_names(&from_parts) {
+        if !content_tagged.is_empty() {
+            bail!("{from:?} is only written in note contents, which renaming leaves alone");
+        }
+        bail!("no note is tagged {from:?}");
+    }
+    if !merge && (in_file_names(&to_parts) || to_in_content) {
+        bail!("the tag {to:?} already exists, merge into it instead");
+    }
+
+    let mut rewrite = TagRewrite {
+        content_tagged,
+        ..TagRewrite::default()
+    };
+    let mut taken = HashSet::new();
+    for path in &paths {
+        let Some(new_path) = rewrite_path(path, &from_parts, &to_parts) else {
+            continue;
+        };
+        let rename = Rename {
+            from: path.clone(),
+            to: new_path,
+        };
+        if taken.contains(&rename.to) || file_exists(state, &rename.to).await? {
+            rewrite.conflicts.push(rename);
+        } else {
+            taken.insert(rename.to.clone());
+            rewrite.notes.push(rename);
+        }
+    }
+
+    let from_key = from_parts.join("--");
+    let to_key = to_parts.join("--");
+    for key in &config_keys {
+        let rest = if *key == from_key {
+            ""
+        } else if let Some(rest) = key.strip_prefix(&format!("{from_key}--")) {
+            rest
+        } else {
+            continue;
+        };
+        let new_key = if rest.is_empty() {
+            to_key.clone()
+        } else {
+            format!("{to_key}--{rest}")
+        };
+        // the config of a tag that is merged into wins, leaving the merged tag's config behind
+        if !config_keys.contains(&new_key) {
+            rewrite.tag_configs.push(Rename {
+                from: key.clone(),
+                to: new_key,
+            });
+        }
+    }
+
+    if dry_run {
+        return Ok(rewrite);
+    }
+    println!("moving tag {from:?} to {to:?}");
+    let mut renamed = Vec::new();
+    for rename in std::mem::take(&mut rewrite.notes) {
+        match update_path(state, rename.from.clone(), path_to_title(&rename.to)).await? {
+            Some(to) => renamed.push(Rename { to, ..rename }),
+            None => rewrite.conflicts.push(rename),
+        }
+    }
+    rewrite.notes = renamed;
+
+    write_meta(state, |holder| {
+        holder.update_meta(|meta| {
+            let configs: Vec<_> = rewrite
+                .tag_configs
+                .iter()
+                .filter_map(|rename| {
+                    let config = meta.tag_configs.remove(&rename.from)?;
+                    Some((rename.to.clone(), config))
+                })
+                .collect();
+            meta.tag_configs.extend(configs);
+        })
+    })
+    .await?;
+    Ok(rewrite)
+}
+
+/// Renames a tag in every note that has it or one of its subtags in its file name, failing if
+/// the new tag is already in use. Tags written in note contents are left alone and listed in
+/// the result. With `dry_run`, only returns what would be renamed.
+pub async fn rename_tag(
+    state: &AppState,
+    from: &str,
+    to: &str,
+    dry_run: bool,
+) -> Result<TagRewrite> {
+    rewrite_tag(state, from, to, false, dry_run).await
+}
+
+/// Like [`rename_tag`], but the tag is moved into `into` even if other notes already have it
+pub async fn merge_tag(
+    state: &AppState,
+    from: &str,
+    into: &str,
+    dry_run: bool,
+) -> Result<TagRewrite> {
+    rewrite_tag(state, from, into, true, dry_run).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{
+        meta::{Meta, read_note_meta},
+        note::NoteMeta,
+    };
+
+    #[tokio::test]
+    async fn renamed_notes_keep_their_meta() {
+        let mut meta = Meta::default();
+        let old = NoteMeta {
+            created: Some(100),
+            open_count: 7,
+            ..NoteMeta::default()
+        };
+        meta.notes.insert("plan_-old.md".to_owned(), old);
+        let state =
+            AppState::in_memory_with_meta(&[("plan_-old.md", "text"), ("idea.md", "#old")], &meta);
+        let rewrite = rename_tag(&state, "-old", "-new", false).await.unwrap();
+        assert_eq!(
+            rewrite.notes,
+            [Rename {
+                from: "plan_-old.md".to_owned(),
+                to: "plan_-new.md".to_owned(),
+            }]
+        );
+        assert_eq!(rewrite.content_tagged, ["idea.md"]);
+
+        let meta = read_note_meta(&state, "plan_-new.md")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(meta.created, Some(100));
+        assert_eq!(meta.open_count, 7);
+        assert!(
+            read_note_meta(&state, "plan_-old.md")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -5,12 +5,24 @@ pub fn path_to_title(path: &str) -> String {
     path.strip_suffix(".md").unwrap_or(&path).to_string()
 }
 
+/// Turns a title like "projects/my note" into "projects/my_note.md", where every `/` separated
+/// part before the last one is a folder
 pub fn title_to_path(title: &str) -> String {
-    let stem = sanitize_filename(title);
+    let stem = title
+        .split('/')
+        .map(sanitize_filename)
+        .filter(|part| !part.is_empty() && part != "." && part != "..")
+        .collect::<Vec<_>>()
+        .join("/");
     format!("{}.md", stem)
 }
 
-fn sanitize_filename(input: &str) -> String {
+/// Returns the file name part of a note path, without any folders
+pub fn path_file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+pub fn sanitize_filename(input: &str) -> String {
     let re = Regex::new(r#"[\s<>:"/\\|?*\x00-\x1F]+"#).unwrap();
     let replaced = re.replace_all(input, "_");
 
@@ -0,0 +1,258 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    message::{
+        folder_manager::{file_exists, free_path, read, remove_file, rename_file},
+        history::record_change,
+        meta::{flush_meta, now_secs, read_meta, update_cached_meta, write_meta},
+        note::NoteMeta,
+        title::path_file_name,
+    },
+    state::AppState,
+};
+
+/// Folder in the vault that trashed notes are moved to. It is a dot-directory, so it is never
+/// listed as part of the vault.
+const TRASH_DIR: &str = ".trash";
+
+/// How long trashed notes are kept by default before purging removes them
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashEntry {
+    pub id: String,
+    /// path the note had before it was trashed, where restoring puts it back
+    pub original_path: String,
+    pub trash_path: String,
+    pub meta: NoteMeta,
+    /// index the note had in the pinned list, if it was pinned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub pinned_position: Option<usize>,
+    /// seconds since the unix epoch
+    #[ts(type = "number")]
+    pub deleted_at: u64,
+}
+
+/// Moves a note into the trash, keeping its meta and pinned position so it can be restored
+pub async fn trash_note(state: &AppState, path: &str) -> Result<()> {
+    let deleted_at = now_secs();
+    let (meta, pinned_position, id) = read_meta(state, |holder| {
+        let meta = holder.meta().notes.get(path).cloned().unwrap_or_default();
+        let pinned_position = holder.meta().pinned.iter().position(|p| p == path);
+        let mut id = deleted_at.to_string();
+        let mut n = 1;
+        while holder.meta().trash.iter().any(|entry| entry.id == id) {
+            n += 1;
+            id = format!("{deleted_at}-{n}");
+        }
+        (meta, pinned_position, id)
+    })
+    .await?;
+    let trash_path = format!("{TRASH_DIR}/{id}/{}", path_file_name(path));
+    // brot.json on disk should match the notes on disk before and after they are moved
+    flush_meta(state).await?;
+    rename_file(state, path, &trash_path).await?;
+    record_change(state, format!("delete {path}")).await;
+
+    let entry = TrashEntry {
+        id,
+        original_path: path.to_owned(),
+        trash_path,
+        meta,
+        pinned_position,
+        deleted_at,
+    };
+    write_meta(state, |holder| {
+        holder.update_meta(|meta| {
+            meta.notes.remove(path);
+            meta.pinned.retain(|p| p != path);
+            meta.trash.push(entry.clone());
+        })
+    })
+    .await?;
+    flush_meta(state).await
+}
+
+pub async fn list_trash(state: &AppState) -> Result<Vec<TrashEntry>> {
+    read_meta(state, |holder| {
+        let mut trash = holder.meta().trash.clone();
+        trash.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+        trash
+    })
+    .await
+}
+
+/// Moves a trashed note back to its original path, or next to it if that path has been taken
+/// since. Returns the path the note was restored to.
+pub async fn restore_trash(state: &AppState, id: &str) -> Result<String> {
+    let entry = read_meta(state, |holder| {
+        holder.meta().trash.iter().find(|e| e.id == id).cloned()
+    })
+    .await?
+    .ok_or_else(|| anyhow!("no trashed note with id {id:?}"))?;
+
+    let path = free_path(state, &entry.original_path).await?;
+    flush_meta(state).await?;
+    rename_file(state, &entry.trash_path, &path).await?;
+    record_change(state, format!("restore {path}")).await;
+
+    write_meta(state, |holder| {
+        holder.update_meta(|meta| {
+            meta.trash.retain(|e| e.id != entry.id);
+            meta.notes.insert(path.clone(), entry.meta.clone());
+            if let Some(position) = entry.pinned_position {
+                let position = position.min(meta.pinned.len());
+                meta.pinned.insert(position, path.clone());
+            }
+        })
+    })
+    .await?;
+    flush_meta(state).await?;
+    if let Some(content) = read(state, &path).await? {
+        update_cached_meta(state, |holder| holder.set_content(&path, &content)).await;
+    }
+    Ok(path)
+}
+
+/// Permanently removes trashed notes deleted more than `max_age_days` ago, falling back to the
+/// retention configured in the settings. Returns the ids of the removed entries.
+pub async fn purge_trash(state: &AppState, max_age_days: Option<u32>) -> Result<Vec<String>> {
+    let max_age_days = match max_age_days {
+        Some(days) => days,
+        None => state
+            .settings
+            .lock()
+            .await
+            .trash_retention_days
+            .unwrap_or(DEFAULT_RETENTION_DAYS),
+    };
+    let cutoff = now_secs().saturating_sub(max_age_days as u64 * 24 * 60 * 60);
+    let expired: Vec<TrashEntry> = read_meta(state, |holder| {
+        holder
+            .meta()
+            .trash
+            .iter()
+            .filter(|entry| entry.deleted_at <= cutoff)
+            .cloned()
+            .collect()
+    })
+    .await?;
+
+    for entry in &expired {
+        if file_exists(state, &entry.trash_path).await? {
+            remove_file(state, &entry.trash_path).await?;
+        }
+    }
+    let ids: Vec<String> = expired.into_iter().map(|entry| entry.id).collect();
+    write_meta(state, |holder| {
+        holder.update_meta(|meta| meta.trash.retain(|entry| !ids.contains(&entry.id)))
+    })
+    .await?;
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{folder_manager::write, meta::Meta};
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    async fn pinned(state: &AppState) -> Vec<String> {
+        read_meta(state, |holder| holder.meta().pinned.clone())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn restoring_puts_the_note_back_where_it_was_pinned() {
+        let mut meta = Meta {
+            pinned: vec!["x.md".to_owned(), "a.md".to_owned(), "y.md".to_owned()],
+            ..Meta::default()
+        };
+        meta.notes.insert(
+            "a.md".to_owned(),
+            NoteMeta {
+                open_count: 5,
+                ..NoteMeta::default()
+            },
+        );
+        let state =
+            AppState::in_memory_with_meta(&[("a.md", "hello"), ("x.md", ""), ("y.md", "")], &meta);
+
+        trash_note(&state, "a.md").await.unwrap();
+        assert!(!file_exists(&state, "a.md").await.unwrap());
+        assert_eq!(pinned(&state).await, ["x.md", "y.md"]);
+        let trash = list_trash(&state).await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].original_path, "a.md");
+        assert_eq!(trash[0].pinned_position, Some(1));
+
+        let path = restore_trash(&state, &trash[0].id).await.unwrap();
+        assert_eq!(path, "a.md");
+        assert_eq!(read(&state, "a.md").await.unwrap().unwrap(), "hello");
+        assert_eq!(pinned(&state).await, ["x.md", "a.md", "y.md"]);
+        let open_count = read_meta(&state, |holder| holder.meta().notes["a.md"].open_count)
+            .await
+            .unwrap();
+        assert_eq!(open_count, 5);
+        assert!(list_trash(&state).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn restoring_onto_a_taken_path_picks_a_free_one() {
+        let state = AppState::in_memory(&[("a.md", "old")]);
+        trash_note(&state, "a.md").await.unwrap();
+        write(&state, "a.md", "new".to_owned()).await.unwrap();
+
+        let id = list_trash(&state).await.unwrap()[0].id.clone();
+        let path = restore_trash(&state, &id).await.unwrap();
+        assert_eq!(path, "a_2.md");
+        assert_eq!(read(&state, "a.md").await.unwrap().unwrap(), "new");
+        assert_eq!(read(&state, "a_2.md").await.unwrap().unwrap(), "old");
+    }
+
+    #[tokio::test]
+    async fn purging_only_removes_old_entries() {
+        let entry = |id: &str, days_ago: u64| TrashEntry {
+            id: id.to_owned(),
+            original_path: format!("{id}.md"),
+            trash_path: format!("{TRASH_DIR}/{id}/{id}.md"),
+            meta: NoteMeta::default(),
+            pinned_position: None,
+            deleted_at: now_secs() - days_ago * DAY,
+        };
+        let meta = Meta {
+            trash: vec![entry("old", 40), entry("recent", 1)],
+            ..Meta::default()
+        };
+        let state = AppState::in_memory_with_meta(
+            &[
+                (".trash/old/old.md", "old"),
+                (".trash/recent/recent.md", "recent"),
+            ],
+            &meta,
+        );
+
+        let purged = purge_trash(&state, Some(30)).await.unwrap();
+        assert_eq!(purged, ["old"]);
+        assert!(!file_exists(&state, ".trash/old/old.md").await.unwrap());
+        assert!(
+            file_exists(&state, ".trash/recent/recent.md")
+                .await
+                .unwrap()
+        );
+        let remaining: Vec<String> = list_trash(&state)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(remaining, ["recent"]);
+    }
+}
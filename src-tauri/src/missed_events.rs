@@ -1,14 +1,66 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::Result;
-use tauri::{AppHandle, Emitter, EventTarget, State};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State};
+use ts_rs::TS;
 
-use crate::state::AppState;
+use crate::{
+    message::{locater::Locater, palette::PaletteResults},
+    state::AppState,
+};
 
-#[derive(Debug, Clone)]
+/// Delivers events to the windows of whatever frontend brot is running in
+pub trait EventSink: Send + Sync {
+    fn emit(&self, label: &str, event: &Event) -> Result<()>;
+    /// Labels of the windows that currently show `locater`
+    fn windows_showing(&self, locater: &Locater) -> Vec<String>;
+}
+
+pub struct TauriEventSink {
+    app: AppHandle,
+}
+
+impl TauriEventSink {
+    pub fn new(app: AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+impl EventSink for TauriEventSink {
+    fn emit(&self, label: &str, event: &Event) -> Result<()> {
+        let target = EventTarget::webview_window(label);
+        match event {
+            Event::Search => self.app.emit_to(target, "search", ())?,
+            Event::NoteChanged(change) => self.app.emit_to(target, "noteChanged", change)?,
+            Event::PaletteResults(results) => {
+                self.app.emit_to(target, "paletteResults", results)?
+            }
+        }
+        Ok(())
+    }
+
+    fn windows_showing(&self, locater: &Locater) -> Vec<String> {
+        self.app
+            .webview_windows()
+            .into_iter()
+            .filter(|(_, window)| {
+                window
+                    .url()
+                    .ok()
+                    .and_then(|url| Locater::from_url(&url))
+                    .is_some_and(|other_locater| other_locater == *locater)
+            })
+            .map(|(label, _)| label)
+            .collect()
+    }
+}
+
+#[derive(Clone)]
 pub struct EventManager {
     window_states: HashMap<String, WindowEventState>,
-    app: AppHandle,
+    /// None when running without any windows, in which case events are dropped
+    sink: Option<Arc<dyn EventSink>>,
 }
 
 #[tauri::command]
@@ -21,10 +73,10 @@ pub fn set_event_ready(window: tauri::Window, state: State<'_, AppState>) {
 }
 
 impl EventManager {
-    pub fn new(app: AppHandle) -> Self {
+    pub fn new(sink: Option<Arc<dyn EventSink>>) -> Self {
         Self {
             window_states: HashMap::new(),
-            app,
+            sink,
         }
     }
     pub fn set_ready(&mut self, label: &str) -> Result<()> {
@@ -65,10 +117,30 @@ impl EventManager {
         Ok(())
     }
 
+    /// Sends the event to every window that currently shows the note at `path` in the vault
+    /// called `vault`
+    pub fn send_event_to_note(&mut self, vault: &str, path: &str, event: Event) -> Result<()> {
+        let Some(sink) = &self.sink else {
+            return Ok(());
+        };
+        let mut labels = sink.windows_showing(&Locater::Note {
+            path: path.to_owned(),
+        });
+        labels.extend(sink.windows_showing(&Locater::VaultNote {
+            vault: vault.to_owned(),
+            path: path.to_owned(),
+        }));
+        for label in labels {
+            self.send_event(&label, event.clone())?;
+        }
+        Ok(())
+    }
+
     fn send_event_ready(&self, label: &str, event: Event) -> Result<()> {
-        Ok(self
-            .app
-            .emit_to(EventTarget::webview_window(label), &event.0, ())?)
+        match &self.sink {
+            Some(sink) => sink.emit(label, &event),
+            None => Ok(()),
+        }
     }
 
     pub fn remove_window(&mut self, label: &str) {
@@ -83,4 +155,24 @@ pub enum WindowEventState {
 }
 
 #[derive(Debug, Clone)]
-pub struct Event(pub String);
+pub enum Event {
+    Search,
+    NoteChanged(NoteChange),
+    PaletteResults(PaletteResults),
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteChange {
+    pub path: String,
+    pub kind: NoteChangeKind,
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub enum NoteChangeKind {
+    Modified,
+    Removed,
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use span_index::SpanIndex;
-use std::{collections::HashMap, ops::Range};
+use std::{collections::HashMap, ops::Range, path::PathBuf};
 use ts_rs::TS;
 use typst::{WorldExt, diag::Warned};
 use utf16::{Utf16Index, to_utf8_range, to_utf16_range};
@@ -22,6 +22,8 @@ pub enum SourceChange {
 
 pub struct Previewer {
     worlds: HashMap<String, SimpleWorld>,
+    /// folder of the active vault, which typst reads images and other project files from
+    project_root: Option<PathBuf>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export)]
@@ -50,8 +52,18 @@ impl Previewer {
     pub fn new() -> Self {
         Self {
             worlds: HashMap::new(),
+            project_root: None,
         }
     }
+    pub fn set_project_root(&mut self, project_root: Option<PathBuf>) {
+        if self.project_root == project_root {
+            return;
+        }
+        for world in self.worlds.values_mut() {
+            world.set_project_root(project_root.clone());
+        }
+        self.project_root = project_root;
+    }
     pub fn update_source(
         &mut self,
         change: SourceChange,
@@ -77,7 +89,7 @@ impl Previewer {
         let world = self
             .worlds
             .entry(editor_view_id)
-            .or_insert(SimpleWorld::new());
+            .or_insert_with(|| SimpleWorld::new(self.project_root.clone()));
 
         let source = world.main_source_mut();
         match change {
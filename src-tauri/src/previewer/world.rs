@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::sync::LazyLock;
 
 use typst::diag::{FileError, FileResult};
@@ -19,7 +20,9 @@ pub struct SimpleWorld {
 }
 
 impl SimpleWorld {
-    pub fn new() -> Self {
+    /// Creates a world whose project files are read from `project_root`, or that has no
+    /// project files if it is None
+    pub fn new(project_root: Option<PathBuf>) -> Self {
         let library = Library::builder()
             .with_features(Features::from_iter([Feature::Html]))
             .build();
@@ -31,17 +34,20 @@ impl SimpleWorld {
                 fonts.extend(fonts::embedded());
                 fonts
             }));
-        let files = SimpleFiles::new();
+        let files = SimpleFiles::new(project_root.clone());
 
         let main_source = Source::new(files.main, "".to_owned());
 
         Self {
             library: LazyHash::new(library),
             fonts,
-            files: FileStore::new(SimpleFiles::new()),
+            files: FileStore::new(SimpleFiles::new(project_root)),
             main_source,
         }
     }
+    pub fn set_project_root(&mut self, project_root: Option<PathBuf>) {
+        self.files = FileStore::new(SimpleFiles::new(project_root));
+    }
     pub fn main_source_mut(&mut self) -> &mut Source {
         &mut self.main_source
     }
@@ -86,11 +92,13 @@ impl World for SimpleWorld {
 struct SimpleFiles {
     main: FileId,
     packages: SystemPackages,
+    /// folder project files like images are loaded from
+    project_root: Option<PathBuf>,
 }
 
 impl SimpleFiles {
     /// Creates a new loader given the configuration.
-    pub fn new() -> SimpleFiles {
+    pub fn new(project_root: Option<PathBuf>) -> SimpleFiles {
         let main = RootedPath::new(
             VirtualRoot::Project,
             VirtualPath::new("main.typ").expect("failed to create virtual path"),
@@ -104,11 +112,12 @@ impl SimpleFiles {
                 FsPackages::system_cache(),
                 UniversePackages::new(downloader()),
             ),
+            project_root,
         }
     }
 
-    /// Resolves the root in which the given file ID resides.
-    fn root(&self, id: FileId) -> FileResult<FsRoot> {
+    /// Resolves the root in which the given package file ID resides.
+    fn package_root(&self, id: FileId) -> FileResult<FsRoot> {
         match id.root() {
             VirtualRoot::Project => {
                 FileResult::Err(FileError::NotFound(id.vpath().get_without_slash().into()))
@@ -116,11 +125,25 @@ impl SimpleFiles {
             VirtualRoot::Package(spec) => Ok(self.packages.obtain(spec)?),
         }
     }
+
+    fn load_project_file(&self, id: FileId) -> FileResult<Bytes> {
+        let relative_path = id.vpath().get_without_slash();
+        let Some(project_root) = &self.project_root else {
+            return FileResult::Err(FileError::NotFound(relative_path.into()));
+        };
+        let path = project_root.join(relative_path);
+        std::fs::read(&path)
+            .map(Bytes::new)
+            .map_err(|err| FileError::from_io(err, &path))
+    }
 }
 
 impl FileLoader for SimpleFiles {
     fn load(&self, id: FileId) -> FileResult<Bytes> {
-        self.root(id)?.load(id.vpath())
+        match id.root() {
+            VirtualRoot::Project => self.load_project_file(id),
+            VirtualRoot::Package(_) => self.package_root(id)?.load(id.vpath()),
+        }
     }
 }
 pub fn downloader() -> impl Downloader {
@@ -1,14 +1,35 @@
 use actix_cors::Cors;
-use actix_files::{Files, NamedFile};
+use actix_files::{file_extension_to_mime, Files, NamedFile};
 use actix_web::{web, App, HttpResponse, HttpServer, Responder, Result};
 
 use crate::{
-    message::{handle_message_and_errors, ClientMessage},
+    message::{
+        attachment::{is_attachment_path, ATTACHMENTS_DIR},
+        folder_manager::read_bytes,
+        handle_message_and_errors, ClientMessage,
+    },
     state::AppState,
 };
 
 async fn message(body: web::Json<ClientMessage>, state: web::Data<AppState>) -> impl Responder {
-    HttpResponse::Ok().json(handle_message_and_errors(body.0, &state).await)
+    HttpResponse::Ok().json(handle_message_and_errors(body.0, &state, None).await)
+}
+
+async fn attachment(path: web::Path<String>, state: web::Data<AppState>) -> HttpResponse {
+    let path = format!("{ATTACHMENTS_DIR}/{}", path.into_inner());
+    if !is_attachment_path(&path) {
+        return HttpResponse::NotFound().finish();
+    }
+    match read_bytes(&state, &path).await {
+        Ok(Some(bytes)) => {
+            let extension = path.rsplit_once('.').map_or("", |(_, extension)| extension);
+            HttpResponse::Ok()
+                .content_type(file_extension_to_mime(extension))
+                .body(bytes)
+        }
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
 }
 
 pub async fn run_server(state: AppState) -> std::io::Result<()> {
@@ -18,6 +39,7 @@ pub async fn run_server(state: AppState) -> std::io::Result<()> {
             .wrap(configure_cors())
             .app_data(state.clone())
             .route("/message", web::post().to(message))
+            .route("/attachments/{path:.*}", web::get().to(attachment))
             .service(Files::new("/", state.build_path.clone()).index_file("index.html"))
             .default_service(web::get().to(fallback_to_index))
     })
@@ -1,21 +1,24 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
-use anyhow::Result;
-use tauri::{App, AppHandle, Manager, path::BaseDirectory};
-use tokio::sync::{Mutex, RwLock};
+use anyhow::{Result, bail};
+use tauri::{App, Manager, path::BaseDirectory};
+use tokio::sync::{Mutex, RwLock, watch};
 
 use crate::{
     message::{
         action::Actions,
-        folder_manager::FolderManager,
+        find_replace::NoteEdit,
+        history::History,
         meta::MetaHolder,
-        palette_action::PaletteAction,
+        palette_action::{PaletteAction, palette_search_fields},
         searcher::SearcherManager,
-        settings::{Settings, read_settings_file},
-        suggester::Suggestion,
+        settings::{Settings, Vault, read_settings_file},
+        store::{NoteStore, StoreOpener, platform_store_opener},
+        suggester::{Suggestion, suggestion_search_fields},
     },
-    missed_events::EventManager,
+    missed_events::{EventManager, TauriEventSink},
     previewer::Previewer,
+    watcher::NoteWatcher,
 };
 
 #[derive(Debug, Clone)]
@@ -31,55 +34,232 @@ pub enum PinnedWindowState {
 pub struct AppState {
     pub build_path: PathBuf,
     pub config_path: PathBuf,
-    pub folder_manager: FolderManager,
-    pub meta: Arc<Mutex<Option<MetaHolder>>>,
+    pub open_store: StoreOpener,
+    /// cached state of every vault that has been opened, by vault name
+    pub vaults: Arc<Mutex<HashMap<String, Arc<VaultState>>>>,
     pub settings: Arc<Mutex<Settings>>,
     pub last_focused_app_name: Arc<Mutex<Option<String>>>,
     pub pinned_state_before_search: Arc<Mutex<PinnedWindowState>>,
-    pub actions: Arc<Mutex<Option<Actions>>>,
-    pub palettes: Arc<RwLock<SearcherManager<PaletteAction>>>,
-    pub suggesters: Arc<RwLock<SearcherManager<Suggestion>>>,
-    pub handle: AppHandle,
     pub event_manager: Arc<Mutex<EventManager>>,
     pub previewer: Arc<Mutex<Previewer>>,
+    pub watcher: Arc<Mutex<Option<NoteWatcher>>>,
+    /// content hash of the last thing brot wrote to each path, or None if brot removed it
+    pub recent_writes: Arc<Mutex<HashMap<String, Option<u64>>>>,
+    /// held while checking a note's revision and writing it, so concurrent writes can't both
+    /// pass the check
+    pub note_write_lock: Arc<Mutex<()>>,
+    /// vault shown by each window that doesn't show the active vault, by window label
+    pub window_vaults: Arc<Mutex<HashMap<String, String>>>,
+    /// vault this state reads and writes, or None for the active vault
+    pub vault_name: Option<String>,
 }
 
 impl AppState {
+    /// Creates the state for the tauri app, with notes kept where the platform keeps them and
+    /// events sent to the app's windows
     pub fn new(app: &mut App) -> Result<Self> {
         let build_path = app.path().resolve("build", BaseDirectory::Resource)?;
         let config_path = app.path().resolve("", BaseDirectory::AppConfig)?;
-        let folder_manager = FolderManager::new(app)?;
+        let open_store = platform_store_opener(app)?;
+        let mut state = Self::headless(build_path, config_path, open_store)?;
+        let sink = TauriEventSink::new(app.handle().clone());
+        state.event_manager = Arc::new(Mutex::new(EventManager::new(Some(Arc::new(sink)))));
+        Ok(state)
+    }
+
+    /// Creates the state without any windows to send events to, reading the settings from
+    /// `config_path` and keeping notes in the stores `open_store` returns
+    pub fn headless(
+        build_path: PathBuf,
+        config_path: PathBuf,
+        open_store: StoreOpener,
+    ) -> Result<Self> {
         let settings = Arc::new(Mutex::new(read_settings_file(&config_path)?));
-        let meta = Arc::new(Mutex::new(None));
+        let vaults = Arc::new(Mutex::new(HashMap::new()));
         let last_focused_app_name = Arc::new(Mutex::new(None));
         let pinned_state_before_search = Arc::new(Mutex::new(PinnedWindowState::Unfocused {
             visible: false,
             last_focused_app_name: None,
         }));
-        let actions = Arc::new(Mutex::new(None));
-        let palettes = Arc::new(RwLock::new(SearcherManager::<PaletteAction>::new(
-            |action| action.title.clone(),
-        )));
-        let suggesters = Arc::new(RwLock::new(SearcherManager::<Suggestion>::new(
-            |suggestion| suggestion.value.clone(),
-        )));
-        let handle = app.handle().clone();
-        let event_manager = Arc::new(Mutex::new(EventManager::new(app.handle().clone())));
+        let event_manager = Arc::new(Mutex::new(EventManager::new(None)));
         let previewer = Arc::new(Mutex::new(Previewer::new()));
+        let watcher = Arc::new(Mutex::new(None));
+        let recent_writes = Arc::new(Mutex::new(HashMap::new()));
+        let note_write_lock = Arc::new(Mutex::new(()));
+        let window_vaults = Arc::new(Mutex::new(HashMap::new()));
         Ok(Self {
             build_path,
             config_path,
-            folder_manager,
+            open_store,
             settings,
-            meta,
+            vaults,
             last_focused_app_name,
             pinned_state_before_search,
-            actions,
-            palettes,
-            suggesters,
-            handle,
             event_manager,
             previewer,
+            watcher,
+            recent_writes,
+            note_write_lock,
+            window_vaults,
+            vault_name: None,
+        })
+    }
+
+    /// The state as seen by the window labeled `window`, which reads and writes the vault bound
+    /// to that window instead of the active one
+    pub async fn for_window(&self, window: Option<&str>) -> Result<Self> {
+        let name = match window {
+            Some(window) => self.window_vaults.lock().await.get(window).cloned(),
+            None => None,
+        };
+        match name {
+            Some(name) => self.for_vault(name).await,
+            None => Ok(self.clone()),
+        }
+    }
+
+    /// The state reading and writing the vault called `name`, whether or not it is active
+    pub async fn for_vault(&self, name: String) -> Result<Self> {
+        if !self
+            .settings
+            .lock()
+            .await
+            .vaults
+            .iter()
+            .any(|vault| vault.name == name)
+        {
+            bail!("there is no vault called {name:?}");
+        }
+        Ok(Self {
+            vault_name: Some(name),
+            ..self.clone()
         })
     }
+
+    /// The state reading and writing whichever vault is active
+    pub fn active(&self) -> Self {
+        Self {
+            vault_name: None,
+            ..self.clone()
+        }
+    }
+
+    /// The state reading and writing the vault this state reads and writes now, even after
+    /// another vault becomes active
+    pub async fn pinned(&self) -> Self {
+        Self {
+            vault_name: self.vault_settings().await.map(|vault| vault.name),
+            ..self.clone()
+        }
+    }
+
+    /// The vault this state reads and writes, if there is one
+    pub async fn vault_settings(&self) -> Option<Vault> {
+        let settings = self.settings.lock().await;
+        match &self.vault_name {
+            Some(name) => settings.vaults.iter().find(|vault| vault.name == *name),
+            None => settings.active_vault(),
+        }
+        .cloned()
+    }
+
+    /// The store of the vault this state reads and writes, if there is one
+    pub async fn store(&self) -> Option<Arc<dyn NoteStore>> {
+        (self.open_store)(self.vault_settings().await.as_ref())
+    }
+
+    /// The cached state of the vault this state reads and writes
+    pub async fn vault(&self) -> Arc<VaultState> {
+        let name = self
+            .vault_settings()
+            .await
+            .map(|vault| vault.name)
+            .unwrap_or_default();
+        self.vaults
+            .lock()
+            .await
+            .entry(name)
+            .or_insert_with(|| Arc::new(VaultState::new()))
+            .clone()
+    }
+}
+
+/// A path in the temporary folder that no other test uses
+#[cfg(test)]
+fn test_dir() -> PathBuf {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    static NEXT_DIR: AtomicU32 = AtomicU32::new(0);
+    std::env::temp_dir().join(format!(
+        "brot-test-{}-{}",
+        std::process::id(),
+        NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State over an in-memory vault holding `files`, with its settings in a new temporary
+    /// folder, for testing without a tauri app or a notes folder
+    pub fn in_memory(files: &[(&str, &str)]) -> Self {
+        use crate::message::store::memory::MemoryStore;
+
+        Self::with_test_store(Arc::new(MemoryStore::with_files(files.iter().copied())))
+    }
+
+    /// State over an empty notes folder in a new temporary folder, for testing what needs
+    /// the notes on disk
+    pub fn on_disk() -> Self {
+        use crate::message::store::desktop::DesktopStore;
+
+        let root = test_dir();
+        std::fs::create_dir_all(&root).expect("failed to create notes folder");
+        Self::with_test_store(Arc::new(DesktopStore::new(root)))
+    }
+
+    fn with_test_store(store: Arc<dyn NoteStore>) -> Self {
+        use crate::message::store::fixed_store_opener;
+
+        Self::headless(PathBuf::new(), test_dir(), fixed_store_opener(store))
+            .expect("failed to create state")
+    }
+
+    /// Like [`Self::in_memory`], with `meta` written to the vault's brot.json
+    pub fn in_memory_with_meta(files: &[(&str, &str)], meta: &crate::message::meta::Meta) -> Self {
+        use crate::message::meta::META_PATH;
+
+        let meta = serde_json::to_string(meta).expect("failed to serialize meta");
+        let mut files = files.to_vec();
+        files.push((META_PATH, &meta));
+        Self::in_memory(&files)
+    }
+}
+
+/// Everything brot loads from a single vault, kept around so switching back to a vault is
+/// instant
+pub struct VaultState {
+    pub meta: Mutex<Option<MetaHolder>>,
+    pub actions: Mutex<Option<Actions>>,
+    pub palettes: RwLock<SearcherManager<PaletteAction>>,
+    pub suggesters: RwLock<SearcherManager<Suggestion>>,
+    /// bumped whenever notes are added, removed or retagged, which open palettes watch
+    pub note_changes: watch::Sender<u64>,
+    /// edits of the last find and replace, for undoing it
+    pub last_replace: Mutex<Option<Vec<NoteEdit>>>,
+    /// changes waiting to be committed to the note history
+    pub history: Mutex<History>,
+}
+
+impl VaultState {
+    fn new() -> Self {
+        Self {
+            meta: Mutex::new(None),
+            actions: Mutex::new(None),
+            palettes: RwLock::new(SearcherManager::new(palette_search_fields())),
+            suggesters: RwLock::new(SearcherManager::new(suggestion_search_fields())),
+            note_changes: watch::Sender::new(0),
+            last_replace: Mutex::new(None),
+            history: Mutex::new(History::default()),
+        }
+    }
 }
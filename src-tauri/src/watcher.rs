@@ -0,0 +1,302 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+use crate::{
+    message::{
+        action::ACTIONS_PATH,
+        folder_manager::{content_hash, read},
+        meta::{META_PATH, now_secs, update_cached_meta},
+        store::is_path_ignored,
+    },
+    missed_events::{Event, NoteChange, NoteChangeKind},
+    state::AppState,
+};
+
+/// How long to wait for more filesystem events before handling a batch
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Keeps the notes folder watched for as long as it is alive
+pub struct NoteWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+/// Starts watching the notes folder of the active vault, replacing any previous watcher. Does
+/// nothing if the notes folder isn't a plain directory on disk.
+pub async fn restart_watcher(state: &AppState) -> Result<()> {
+    // the settings may be changed from a window bound to another vault
+    let state = &state.active();
+    let mut guard = state.watcher.lock().await;
+    *guard = None;
+
+    let Some(root) = state
+        .store()
+        .await
+        .and_then(|store| store.root().map(Path::to_path_buf))
+    else {
+        return Ok(());
+    };
+    let root = std::fs::canonicalize(&root).unwrap_or(root);
+
+    let (sender, receiver) = unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = sender.send(res);
+    })?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+    println!("watching {root:?}");
+
+    tauri::async_runtime::spawn(handle_events(state.clone(), root, receiver));
+    *guard = Some(NoteWatcher { _watcher: watcher });
+    Ok(())
+}
+
+async fn handle_events(
+    state: AppState,
+    root: PathBuf,
+    mut receiver: UnboundedReceiver<notify::Result<notify::Event>>,
+) {
+    while let Some(first) = receiver.recv().await {
+        tokio::time::sleep(DEBOUNCE).await;
+
+        let mut paths = HashSet::new();
+        let mut add_paths = |res: notify::Result<notify::Event>| match res {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                paths.extend(
+                    event
+                        .paths
+                        .iter()
+                        .filter_map(|path| relative_path(&root, path)),
+                );
+            }
+            Ok(_) => {}
+            Err(err) => println!("watcher err: {err}"),
+        };
+        add_paths(first);
+        while let Ok(res) = receiver.try_recv() {
+            add_paths(res);
+        }
+
+        for path in paths {
+            if let Err(err) = handle_change(&state, &path).await {
+                println!("failed to handle change of {path:?}: {err}");
+            }
+        }
+    }
+}
+
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+async fn handle_change(state: &AppState, path: &str) -> Result<()> {
+    let ignored = state
+        .settings
+        .lock()
+        .await
+        .ignored_paths
+        .clone()
+        .unwrap_or_default();
+    let is_note = path.ends_with(".md") && !is_path_ignored(path, &ignored);
+    if !is_note && path != META_PATH && path != ACTIONS_PATH.trim_start_matches("./") {
+        return Ok(());
+    }
+
+    let contents = read(state, path).await?;
+    let changed_externally =
+        state.recent_writes.lock().await.get(path) != Some(&contents.as_deref().map(content_hash));
+
+    if path == META_PATH {
+        if changed_externally {
+            let vault = state.vault().await;
+            let mut guard = vault.meta.lock().await;
+            // changes that haven't been flushed yet are newer than the file, so they win
+            if guard.as_ref().is_none_or(|holder| !holder.is_dirty()) {
+                *guard = None;
+            }
+        }
+        return Ok(());
+    }
+    if !is_note {
+        *state.vault().await.actions.lock().await = None;
+        return Ok(());
+    }
+
+    let kind = match &contents {
+        Some(contents) => {
+            let now = now_secs();
+            update_cached_meta(state, |holder| {
+                if !holder.meta().notes.contains_key(path) || changed_externally {
+                    holder.update_meta(|meta| {
+                        let note_meta = meta.notes.entry(path.to_owned()).or_default();
+                        if changed_externally {
+                            note_meta.record_content(contents, now);
+                        }
+                    });
+                    holder.set_content(path, contents);
+                }
+            })
+            .await;
+            NoteChangeKind::Modified
+        }
+        None => {
+            update_cached_meta(state, |holder| {
+                if holder.meta().notes.contains_key(path) {
+                    holder.update_meta(|meta| {
+                        meta.notes.remove(path);
+                    });
+                }
+            })
+            .await;
+            NoteChangeKind::Removed
+        }
+    };
+
+    if changed_externally {
+        println!("note changed externally: {path:?}");
+        let vault = state
+            .settings
+            .lock()
+            .await
+            .active_vault()
+            .map(|vault| vault.name.clone())
+            .unwrap_or_default();
+        state.event_manager.lock().await.send_event_to_note(
+            &vault,
+            path,
+            Event::NoteChanged(NoteChange {
+                path: path.to_owned(),
+                kind,
+            }),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{
+        message::{
+            folder_manager::write,
+            locater::Locater,
+            meta::{Meta, flush_meta, read_meta, write_meta},
+        },
+        missed_events::{EventManager, EventSink},
+    };
+
+    const WINDOW: &str = "main";
+
+    /// Shows every note in one window and keeps the note changes sent to it
+    #[derive(Default)]
+    struct RecordingSink {
+        changes: Mutex<Vec<(String, NoteChangeKind)>>,
+    }
+
+    impl EventSink for RecordingSink {
+        fn emit(&self, _label: &str, event: &Event) -> Result<()> {
+            if let Event::NoteChanged(change) = event {
+                let mut changes = self.changes.lock().unwrap();
+                changes.push((change.path.clone(), change.kind));
+            }
+            Ok(())
+        }
+
+        fn windows_showing(&self, locater: &Locater) -> Vec<String> {
+            match locater {
+                Locater::Note { .. } => vec![WINDOW.to_owned()],
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    async fn watched_state(files: &[(&str, &str)]) -> (AppState, Arc<RecordingSink>) {
+        let mut state = AppState::in_memory(files);
+        let sink = Arc::new(RecordingSink::default());
+        let mut events = EventManager::new(Some(sink.clone()));
+        events.set_ready(WINDOW).unwrap();
+        state.event_manager = Arc::new(tokio::sync::Mutex::new(events));
+        // load the meta, like it is by the time anything is watched
+        read_meta(&state, |_| ()).await.unwrap();
+        (state, sink)
+    }
+
+    async fn write_externally(state: &AppState, path: &str, contents: &str) {
+        state.store().await.unwrap().write(path, contents).unwrap();
+    }
+
+    #[tokio::test]
+    async fn external_edits_update_the_meta() {
+        let (state, sink) = watched_state(&[("a.md", "one")]).await;
+        write_externally(&state, "a.md", "one two three").await;
+        handle_change(&state, "a.md").await.unwrap();
+
+        let word_count = read_meta(&state, |holder| holder.meta().notes["a.md"].word_count)
+            .await
+            .unwrap();
+        assert_eq!(word_count, 3);
+        let changes = sink.changes.lock().unwrap().clone();
+        assert_eq!(changes, [("a.md".to_owned(), NoteChangeKind::Modified)]);
+    }
+
+    #[tokio::test]
+    async fn own_writes_are_ignored() {
+        let (state, sink) = watched_state(&[("a.md", "one")]).await;
+        write(&state, "a.md", "one two".to_owned()).await.unwrap();
+        handle_change(&state, "a.md").await.unwrap();
+
+        assert!(sink.changes.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn deleted_notes_are_removed() {
+        let (state, sink) = watched_state(&[("a.md", "one")]).await;
+        state.store().await.unwrap().remove("a.md").unwrap();
+        handle_change(&state, "a.md").await.unwrap();
+
+        let known = read_meta(&state, |holder| holder.meta().notes.contains_key("a.md"))
+            .await
+            .unwrap();
+        assert!(!known);
+        let changes = sink.changes.lock().unwrap().clone();
+        assert_eq!(changes, [("a.md".to_owned(), NoteChangeKind::Removed)]);
+    }
+
+    async fn pinned(state: &AppState) -> Vec<String> {
+        read_meta(state, |holder| holder.meta().pinned.clone())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_changed_meta_file_keeps_unflushed_meta() {
+        let (state, _) = watched_state(&[("a.md", "")]).await;
+        write_meta(&state, |holder| {
+            holder.update_meta(|meta| meta.pinned.push("a.md".to_owned()))
+        })
+        .await
+        .unwrap();
+        let empty = serde_json::to_string(&Meta::default()).unwrap();
+        write_externally(&state, META_PATH, &empty).await;
+        handle_change(&state, META_PATH).await.unwrap();
+        assert_eq!(pinned(&state).await, ["a.md"]);
+
+        // once it is flushed, the file is read again
+        flush_meta(&state).await.unwrap();
+        write_externally(&state, META_PATH, &empty).await;
+        handle_change(&state, META_PATH).await.unwrap();
+        assert!(pinned(&state).await.is_empty());
+    }
+}
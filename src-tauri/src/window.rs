@@ -6,6 +6,8 @@ use tauri::{
     AppHandle, Manager, PhysicalPosition, PhysicalSize, State, WebviewWindow, WindowEvent,
 };
 
+use crate::message::meta::flush_all_meta;
+use crate::message::searcher::delete_window_searchers;
 use crate::missed_events::Event;
 use crate::state::PinnedWindowState;
 use crate::{message::locater::Locater, state::AppState};
@@ -203,9 +205,20 @@ pub fn open_and_get_window(
     window.set_focus().unwrap();
 
     let event_manager = state.event_manager.clone();
+    let app_state = state.inner().clone();
     window.on_window_event(move |event| match event {
         WindowEvent::CloseRequested { .. } => {
             event_manager.blocking_lock().remove_window(&label);
+            let app_state = app_state.clone();
+            let label = label.clone();
+            tauri::async_runtime::spawn(async move {
+                delete_window_searchers(&app_state, &label).await;
+                app_state.window_vaults.lock().await.remove(&label);
+                // the window may have shown a vault other than the active one
+                if let Err(err) = flush_all_meta(&app_state).await {
+                    println!("failed to write meta: {err}");
+                }
+            });
         }
         _ => {}
     });
@@ -250,7 +263,7 @@ pub fn open_search(app: AppHandle, state: State<'_, AppState>) {
     state
         .event_manager
         .blocking_lock()
-        .send_event(window.label(), Event("search".to_owned()))
+        .send_event(window.label(), Event::Search)
         .expect("failed to send search event");
 }
 
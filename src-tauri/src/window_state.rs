@@ -2,7 +2,10 @@ use serde::{Deserialize, Serialize};
 use tauri::State;
 use ts_rs::TS;
 
-use crate::{message::locater::Locater, state::AppState};
+use crate::{
+    message::{locater::Locater, settings::write_settings_file},
+    state::AppState,
+};
 
 #[derive(Serialize, Deserialize, TS, Clone, PartialEq, Eq)]
 pub struct WindowState {
@@ -49,11 +52,9 @@ pub async fn update_window_state(
     {
         settings.window_states.insert(locater, window_state);
 
-        tokio::fs::write(
-            state.config_path.join("settings.json"),
-            serde_json::to_string(&*settings).unwrap(),
-        )
-        .await?;
+        let config_path = state.config_path.clone();
+        let settings = settings.clone();
+        tokio::task::spawn_blocking(move || write_settings_file(&config_path, &settings)).await??;
     }
 
     Ok(())
@@ -14,6 +14,7 @@ export const actions = {
 	saveNote: [],
 	saveWindowState: [],
 	refresh: [],
+	switchVault: ["vault"],
 	refreshPage: [],
 	refreshTagConfigs: [],
 	toggleFloating: [],
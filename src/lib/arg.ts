@@ -12,6 +12,7 @@ import {
 export type ArgTypesMap = {
 	locater: Locater;
 	notePath: string;
+	vault: string;
 	insertion: "above" | "below";
 	boolean: boolean;
 	palette: string;
@@ -35,6 +36,7 @@ export const parsers: {
 	[K in keyof ArgTypesMap]: (val: string) => ArgTypesMap[K];
 } = {
 	notePath: (val) => val,
+	vault: (val) => val,
 	insertion: (val) => verifyEnum(val, ["above", "below"]),
 	level: (val) => verifyEnum(Number(val), [1, 2, 3, 4, 5, 6]),
 	boolean: (val) => val === "true",
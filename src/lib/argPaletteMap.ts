@@ -47,6 +47,7 @@ export const argPaletteMap: ArgPaletteMap = {
   }),
   locater: withProps(UnsupportedArg<"locater">, { argType: "locater" }),
   notePath: withProps(UnsupportedArg<"notePath">, { argType: "notePath" }),
+  vault: withProps(UnsupportedArg<"vault">, { argType: "vault" }),
   lang: withProps(TextChecker<string>, { toVal: parseLangFromString }),
   latex: withProps(TextChecker<LatexRender>, {
     toVal: parseLatexRenderFromString,
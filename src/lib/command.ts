@@ -12,6 +12,11 @@ import { type WithProps } from "./componentProps";
 import type { SearcherId } from "../../src-tauri/bindings/SearcherId";
 import { type ArgTypesMap } from "./arg";
 import { argPaletteMap } from "./argPaletteMap";
+import { listen } from "@tauri-apps/api/event";
+import { isTauri } from "./platform";
+import type { PaletteAction } from "../../src-tauri/bindings/PaletteAction";
+import type { PaletteResults } from "../../src-tauri/bindings/PaletteResults";
+import type { SearchResults } from "../../src-tauri/bindings/SearchResults";
 
 export interface CommandProvider<T> {
   search: (
@@ -19,6 +24,7 @@ export interface CommandProvider<T> {
     start: number,
     end: number
   ) => Promise<CommandChoice<T>[]>;
+  choose?: (choice: CommandChoice<T>) => void;
   stop?: () => void;
 }
 
@@ -56,6 +62,49 @@ export function addArg<T extends keyof ArgTypesMap, K extends ActionsKey>(
   };
 }
 
+/** how long a search still being matched waits for its complete results */
+const MATCHED_TIMEOUT_MS = 1000;
+
+type PaletteSearch = { search: string; start: number; end: number };
+
+/**
+ * Listens for the complete results of a palette search, which the backend sends when it
+ * answered the search before it was done matching. Resolves to null if none come in time.
+ */
+async function listenForMatched(
+  id: () => SearcherId | null,
+  { search, start, end }: PaletteSearch
+): Promise<{
+  results: Promise<SearchResults<PaletteAction> | null>;
+  stop: () => void;
+}> {
+  if (!isTauri()) {
+    return { results: Promise.resolve(null), stop: () => {} };
+  }
+  let resolve: (results: SearchResults<PaletteAction> | null) => void = () => {};
+  let results = new Promise<SearchResults<PaletteAction> | null>((r) => {
+    resolve = r;
+  });
+  let unlisten = await listen<PaletteResults>("paletteResults", (event) => {
+    let matched = event.payload;
+    if (
+      matched.id == id() &&
+      matched.search == search &&
+      matched.start == start &&
+      matched.end == end
+    ) {
+      resolve(matched.results);
+    }
+  });
+  let timeout = setTimeout(() => resolve(null), MATCHED_TIMEOUT_MS);
+  let stop = () => {
+    clearTimeout(timeout);
+    unlisten();
+    resolve(null);
+  };
+  return { results, stop };
+}
+
 class PaletteCommandProvider implements CommandProvider<PartialAction> {
   id: SearcherId | null;
   key: string;
@@ -92,17 +141,24 @@ class PaletteCommandProvider implements CommandProvider<PartialAction> {
     if (this.id == null) {
       this.id = await this.initId();
     }
+    // listen before searching, so results sent right after the search aren't missed
+    let matched = await listenForMatched(() => this.id, { search, start, end });
     let res = await this.searchPalette(search, this.id, start, end);
     // if null, then id is invalid, so try reiniting
     if (res == null) {
       this.id = await this.initId();
       res = await this.searchPalette(search, this.id, start, end);
     }
+    // wait for matching to finish, so results don't come in partial
+    if (res?.running) {
+      res = (await matched.results) ?? res;
+    }
+    matched.stop();
     // if still null then we are cooked
     if (res == null) {
       return [];
     }
-    return res.map((matched) => {
+    return res.matches.map((matched) => {
       let {
         indices,
         payload: { title, icon, shortcut, action, path },
@@ -111,6 +167,10 @@ class PaletteCommandProvider implements CommandProvider<PartialAction> {
     });
   }
 
+  async choose(choice: CommandChoice<PartialAction>): Promise<void> {
+    await msg("chooseAction", { action: choice.payload, path: choice.path });
+  }
+
   async stop(): Promise<void> {
     if (this.id == null) return;
     await msg("deletePalette", { id: this.id });
@@ -7,7 +7,15 @@ export function locaterToUrl(locater: Locater): string {
     return "/settings";
   } else if (locater == "new") {
     return "/new";
+  } else if (locater.startsWith("vault:")) {
+    let [vault, path] = splitOnce(locater.slice(6), ":note:");
+    return "/note?p=" + path + "&v=" + vault;
   } else {
     return "/note?p=" + locater.slice(5);
   }
 }
+
+function splitOnce(s: string, separator: string): [string, string] {
+  let index = s.indexOf(separator);
+  return [s.slice(0, index), s.slice(index + separator.length)];
+}
@@ -60,12 +60,19 @@ export class TagSuggestionProvider implements SuggestionProvider {
 
     return res.map((matched) => {
       let {
-        indices,
-        payload: { value },
+        fields,
+        payload: { value, alias },
       } = matched;
+      let fieldIndices = (field: string) =>
+        fields.find((fieldMatch) => fieldMatch.field == field)?.indices ?? [];
+      let aliasPrefix = alias == null ? "" : `${alias} → `;
       return {
-        indices,
-        display: value,
+        // the alias is shown in front of the value, so the value's indices are shifted past it
+        indices: [
+          ...fieldIndices("alias"),
+          ...fieldIndices("value").map((i) => i + aliasPrefix.length),
+        ],
+        display: aliasPrefix + value,
         replace: () => {
           return {
             text: text.substring(0, start) + value + text.substring(end),